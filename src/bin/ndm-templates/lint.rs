@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Problem;
+use not_dank_memer::util::image::{Dimension, EffectRegistry, ImageStorage};
+use not_dank_memer::util::image::feature::FeatureType;
+
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+// NO TEMPLATES IN THERE
+const RESOURCE_DIRECTORIES: [&str; 2] = ["fonts", "emoji"];
+
+/// Checks what the parser cannot: images which no template uses, overlay images, and boxes which
+/// overlap or stick out of the base image
+pub fn lint(images: &ImageStorage, templates: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    let mut keys = images.get_all_keys();
+    keys.sort();
+    for key in keys.iter() {
+        problems.append(&mut lint_template(images, key));
+    }
+
+    let mut ignored: Vec<PathBuf> = RESOURCE_DIRECTORIES.iter().map(|d| templates.join(d)).collect();
+    ignored.extend(EffectRegistry::load(templates).names().iter().map(|n| templates.join(n)));
+    let ignored: Vec<PathBuf> = ignored.iter().filter_map(|p| p.canonicalize().ok()).collect();
+    let referenced = referenced_paths(templates);
+    for orphan in orphan_images(templates, &ignored, &referenced) {
+        problems.push(Problem::warning(format!("{} is not used by any template", orphan.display())));
+    }
+
+    problems
+}
+
+fn lint_template(images: &ImageStorage, key: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let (width, height) = match images.base_size(key) {
+        Some(s) => s,
+        None => return problems
+    };
+    let features = images.get_required_features_with_duplicates(key).unwrap_or_default();
+
+    for f in features.iter() {
+        let d = &f.dimension;
+        if d.w == 0 || d.h == 0 {
+            problems.push(Problem::error(format!(r#"feature "{}" in template "{}" has an empty box"#, f.key, key)));
+        }
+        if outside(d, width, height) {
+            problems.push(Problem::warning(format!(r#"feature "{}" in template "{}" sticks out of the base image ({}x{})"#, f.key, key, width, height)));
+        }
+
+        if let Some(ref path) = f.overlay_image_path {
+            // LIKE THE BOT, THE FORMAT IS GUESSED FROM THE CONTENT AND NOT FROM THE EXTENSION
+            let decoded = fs::read(path).map_err(|e| e.to_string()).and_then(|buf| image::load_from_memory(&buf).map_err(|e| e.to_string()));
+            if let Err(e) = decoded {
+                problems.push(Problem::error(format!(r#"overlay image "{}" of feature "{}" in template "{}" cannot be read: {}"#, path, f.key, key, e)));
+            }
+        }
+    }
+
+    // OVERLAY IMAGES COVER OTHER FEATURES AND CAPTIONS ARE DRAWN ONTO AVATARS ON PURPOSE.
+    // TWO TEXTS OR TWO USER IMAGES ON TOP OF EACH OTHER ARE MOST LIKELY A MISTAKE
+    let boxes: Vec<_> = features.iter().filter(|f| f.kind != FeatureType::Image).collect();
+    for (i, a) in boxes.iter().enumerate() {
+        for b in boxes.iter().skip(i + 1) {
+            let same_kind = (a.kind == FeatureType::UserImage) == (b.kind == FeatureType::UserImage);
+            if a.key != b.key && same_kind && overlap(&a.dimension, &b.dimension) {
+                problems.push(Problem::warning(format!(r#"features "{}" and "{}" in template "{}" overlap"#, a.key, b.key, key)));
+            }
+        }
+    }
+
+    problems
+}
+
+fn outside(d: &Dimension, width: u32, height: u32) -> bool {
+    match d.quad {
+        Some(ref q) => q.0.iter().any(|&(x, y)| x < 0.0 || y < 0.0 || x > width as f32 || y > height as f32),
+        None => d.x + d.w > width || d.y + d.h > height
+    }
+}
+
+fn overlap(a: &Dimension, b: &Dimension) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+/// Every existing file or directory any template file mentions (e.g. overlay images, mask images, effect stickers)
+fn referenced_paths(dir: &Path) -> HashSet<PathBuf> {
+    let mut paths = HashSet::new();
+    for entry in read_dir(dir) {
+        if entry.is_dir() {
+            paths.extend(referenced_paths(&entry));
+            continue;
+        }
+        if entry.extension().is_none_or(|e| e != "toml") {
+            continue;
+        }
+        let value: toml::Value = match fs::read_to_string(&entry).ok().and_then(|s| toml::from_str(&s).ok()) {
+            Some(s) => s,
+            None => continue // THE PARSER ALREADY COMPLAINS ABOUT IT
+        };
+        let mut strings = Vec::new();
+        collect_strings(&value, &mut strings);
+        for s in strings {
+            // EFFECTS LIKE "overlay:0.5:<path>" HAVE THE PATH AT THE END
+            let candidate = s.rsplit(':').next().unwrap_or(s);
+            if let Ok(path) = Path::new(candidate).canonicalize() {
+                paths.insert(path);
+            }
+        }
+    }
+    paths
+}
+
+fn collect_strings<'a>(value: &'a toml::Value, out: &mut Vec<&'a str>) {
+    match *value {
+        toml::Value::String(ref s) => out.push(s),
+        toml::Value::Array(ref a) => a.iter().for_each(|v| collect_strings(v, out)),
+        toml::Value::Table(ref t) => t.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Images which are neither the base image of a template file next to them nor referenced by any template
+fn orphan_images(dir: &Path, ignored: &[PathBuf], referenced: &HashSet<PathBuf>) -> Vec<PathBuf> {
+    let mut orphans = Vec::new();
+    let canonical = match dir.canonicalize() {
+        Ok(k) => k,
+        Err(_) => return orphans
+    };
+    if ignored.contains(&canonical) || referenced.contains(&canonical) {
+        return orphans;
+    }
+
+    let entries = read_dir(dir);
+    let templates: Vec<&str> = entries.iter()
+        .filter(|e| e.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|e| e.file_stem().and_then(|s| s.to_str()))
+        .collect();
+    for entry in entries.iter() {
+        if entry.is_dir() {
+            orphans.append(&mut orphan_images(entry, ignored, referenced));
+            continue;
+        }
+        let is_image = entry.extension().and_then(|e| e.to_str()).is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        let stem = entry.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        if !is_image || templates.contains(&stem) {
+            continue;
+        }
+        if entry.canonicalize().map_or(true, |p| !referenced.contains(&p)) {
+            orphans.push(entry.clone());
+        }
+    }
+    orphans
+}
+
+/// Sorted, so the output is the same on every run
+fn read_dir(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(k) => k.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => vec![]
+    };
+    entries.sort();
+    entries
+}
\ No newline at end of file
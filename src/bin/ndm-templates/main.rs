@@ -0,0 +1,111 @@
+//! Checks the image templates and renders a preview of each of them.
+//! Run it from the directory the bot runs in, the paths in the templates are relative to it.
+//!
+//! Usage: ndm-templates [templates directory] [--out <preview directory>] [--no-render]
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::PathBuf;
+use std::process;
+
+use not_dank_memer::util::image::ImageStorage;
+
+mod lint;
+mod render;
+
+const DEFAULT_TEMPLATES: &str = "./templates/";
+const DEFAULT_OUT: &str = "./template_previews/";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    // THE TEMPLATE IS SKIPPED OR CANNOT BE RENDERED
+    Error,
+    // THE TEMPLATE WORKS BUT MOST LIKELY NOT AS INTENDED
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn error(message: String) -> Self {
+        Problem { severity: Severity::Error, message }
+    }
+
+    pub fn warning(message: String) -> Self {
+        Problem { severity: Severity::Warning, message }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+struct Options {
+    templates: PathBuf,
+    out: PathBuf,
+    render: bool,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(k) => k,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: ndm-templates [templates directory] [--out <preview directory>] [--no-render]");
+            process::exit(2);
+        }
+    };
+
+    let images = match ImageStorage::load(&options.templates) {
+        Ok(k) => k,
+        Err(e) => {
+            eprintln!("error: could not load the templates: {}", e);
+            process::exit(1);
+        }
+    };
+
+    // EVERY WARNING OF THE PARSER MEANS THAT A TEMPLATE OR A PART OF IT IS IGNORED
+    let mut problems: Vec<Problem> = images.warnings().iter().map(|w| Problem::error(w.clone())).collect();
+    problems.append(&mut lint::lint(&images, &options.templates));
+    if options.render {
+        problems.append(&mut render::render_all(&images, &options.out));
+    }
+
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    let errors = problems.iter().filter(|p| p.severity == Severity::Error).count();
+    println!("{} templates, {} error(s), {} warning(s)", images.get_all_keys().len(), errors, problems.len() - errors);
+    if options.render {
+        println!("Previews: {}", options.out.display());
+    }
+
+    if errors > 0 {
+        process::exit(1);
+    }
+}
+
+fn parse_options<I: Iterator<Item=String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        templates: PathBuf::from(DEFAULT_TEMPLATES),
+        out: PathBuf::from(DEFAULT_OUT),
+        render: true,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => options.out = PathBuf::from(args.next().ok_or("--out needs a directory")?),
+            "--no-render" => options.render = false,
+            a if a.starts_with("--") => return Err(format!("unknown option {}", a)),
+            a => options.templates = PathBuf::from(a),
+        }
+    }
+    Ok(options)
+}
\ No newline at end of file
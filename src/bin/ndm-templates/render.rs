@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::Path;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::Problem;
+use not_dank_memer::util::image::{ImageStorage, set_debug};
+use not_dank_memer::util::image::animation::ImageFrames;
+use not_dank_memer::util::image::feature::FeatureType;
+
+const AVATAR_SIZE: u32 = 128;
+// ONE COLOR PER USER IMAGE, SO THEY CAN BE TOLD APART
+const AVATAR_COLORS: [[u8; 4]; 4] = [[231, 76, 60, 255], [52, 152, 219, 255], [46, 204, 113, 255], [241, 196, 15, 255]];
+
+/// Renders every template with placeholder avatars and texts into the directory. The boxes of the features are drawn, too
+pub fn render_all(images: &ImageStorage, out: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if let Err(e) = fs::create_dir_all(out) {
+        problems.push(Problem::error(format!("could not create the preview directory {}: {}", out.display(), e)));
+        return problems;
+    }
+    set_debug(true);
+
+    let mut keys = images.get_all_keys();
+    keys.sort();
+    for key in keys.iter() {
+        if let Err(e) = render(images, key, out) {
+            problems.push(Problem::error(format!(r#"template "{}" could not be rendered: {}"#, key, e)));
+        }
+    }
+    problems
+}
+
+fn render(images: &ImageStorage, key: &str, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut template = images.start_building(key).ok_or("template not found")?;
+    let features = images.get_required_features(key).ok_or("template not found")?;
+
+    let mut avatars = 0;
+    for f in features.iter() {
+        match f.kind {
+            FeatureType::Image => template.set_image(&f.key)?,
+            FeatureType::SplitText => template.set_text(&f.key, format!("Text for {}", f.key))?,
+            FeatureType::Text => template.set_text(&f.key, format!("The quick brown fox jumps over the lazy dog ({})", f.key))?,
+            FeatureType::UserImage => {
+                template.set_user_image(&f.key, ImageFrames::still(placeholder_avatar(avatars)))?;
+                avatars += 1;
+            }
+        }
+    }
+
+    let template = template.build()?;
+    let extension = if template.is_animated() { "gif" } else { "png" };
+    fs::write(out.join(format!("{}.{}", key, extension)), template.apply()?)?;
+    Ok(())
+}
+
+/// A checkerboard with a white corner in the upper left, so cropping and anchors can be seen
+fn placeholder_avatar(index: usize) -> DynamicImage {
+    let color = Rgba(AVATAR_COLORS[index % AVATAR_COLORS.len()]);
+    let dark = Rgba([color[0] / 2, color[1] / 2, color[2] / 2, 255]);
+    let corner = AVATAR_SIZE / 4;
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(AVATAR_SIZE, AVATAR_SIZE, |x, y| {
+        if x < corner && y < corner {
+            Rgba([255, 255, 255, 255])
+        } else if (x / 16 + y / 16) % 2 == 0 {
+            color
+        } else {
+            dark
+        }
+    }))
+}
\ No newline at end of file
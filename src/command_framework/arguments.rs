@@ -0,0 +1,522 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use serenity::model::guild::Member;
+use serenity::model::id::UserId;
+use serenity::model::user::User;
+
+use super::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum ArgumentKind {
+    /// A mentioned user (@User) or the ID of a user
+    User,
+    /// A mentioned user or the ID of a user who has to be a member of the current guild
+    Member,
+    /// A whole number
+    Integer,
+    /// A single word or multiple words wrapped in quotes ("like this")
+    Quoted,
+    /// Everything until the end of the message
+    Rest,
+    /// Text until the next comma. Consecutive delimited arguments form a comma-delimited list ("first, second")
+    Delimited,
+    /// Everything until the end of the message, split at commas ("first, second, third")
+    List,
+    /// A mentioned user (@User) for their avatar, an image URL or a custom emoji.
+    /// May be left out even if it is required. The command looks for an image somewhere else then (e.g. an attachment)
+    Image,
+}
+
+impl ArgumentKind {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::User | Self::Member => "@User",
+            Self::Integer => "Number",
+            Self::Quoted | Self::Rest | Self::Delimited => "Text",
+            Self::List => "Text, ...",
+            Self::Image => "@User/Image",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgumentSpec {
+    pub name: &'static str,
+    pub kind: ArgumentKind,
+    pub optional: bool,
+}
+
+impl ArgumentSpec {
+    pub const fn new(name: &'static str, kind: ArgumentKind) -> Self {
+        ArgumentSpec {
+            name,
+            kind,
+            optional: false,
+        }
+    }
+
+    pub const fn optional(name: &'static str, kind: ArgumentKind) -> Self {
+        ArgumentSpec {
+            name,
+            kind,
+            optional: true,
+        }
+    }
+}
+
+/// Builds the usage string (e.g. "[<lover: @User>] <loved: @User>") out of the argument specs
+pub fn usage(specs: &[ArgumentSpec]) -> String {
+    let mut buf = String::new();
+    for spec in specs.iter() {
+        let mut s = format!("<{}: {}>", spec.name, spec.kind.label());
+        if spec.optional {
+            s = format!("[{}]", s);
+        }
+        buf.push_str(&s);
+        if spec.kind == ArgumentKind::Delimited {
+            buf.push(',');
+        }
+        buf.push(' ');
+    }
+
+    let trimmed = buf.trim_end().trim_end_matches(',').len();
+    buf.truncate(trimmed);
+    buf
+}
+
+#[derive(Clone, Debug)]
+pub enum ArgumentValue {
+    User(User),
+    Member(Member),
+    Integer(i64),
+    Text(String),
+    List(Vec<String>),
+    Image(ImageArgument),
+}
+
+#[derive(Clone, Debug)]
+pub enum ImageArgument {
+    // THE AVATAR OF THE USER IS USED
+    User(User),
+    Url(String),
+    // THE URL OF THE EMOJI'S IMAGE
+    Emoji(String),
+}
+
+/// The parsed arguments of a command. Optional arguments which were not specified are missing
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    values: Vec<(&'static str, ArgumentValue)>
+}
+
+#[allow(dead_code)]
+impl Args {
+    pub(crate) fn push(&mut self, name: &'static str, value: ArgumentValue) {
+        self.values.push((name, value));
+    }
+
+    fn get(&self, name: &str) -> Option<&ArgumentValue> {
+        self.values.iter().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    pub fn user(&self, name: &str) -> Option<&User> {
+        match self.get(name)? {
+            ArgumentValue::User(u) => Some(u),
+            _ => None
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        match self.get(name)? {
+            ArgumentValue::Member(m) => Some(m),
+            _ => None
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            ArgumentValue::Integer(i) => Some(*i),
+            _ => None
+        }
+    }
+
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            ArgumentValue::Text(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub fn list(&self, name: &str) -> Option<&[String]> {
+        match self.get(name)? {
+            ArgumentValue::List(l) => Some(l),
+            _ => None
+        }
+    }
+
+    pub fn image(&self, name: &str) -> Option<&ImageArgument> {
+        match self.get(name)? {
+            ArgumentValue::Image(i) => Some(i),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ArgumentError {
+    // Argument name
+    Missing(&'static str),
+    // Argument name, what is wrong
+    Invalid(&'static str, String),
+    // The input which is left over
+    TooMany(String),
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Missing(name) => write!(f, "Missing argument ``{}``", name),
+            Self::Invalid(name, ref e) => write!(f, "Invalid argument ``{}``: {}", name, e),
+            Self::TooMany(ref rest) => write!(f, "Too many arguments: ``{}``", rest),
+        }
+    }
+}
+
+impl std::error::Error for ArgumentError {}
+
+/// Finds the users of mentions and IDs. Tests use fixed users instead of the cache
+pub(crate) trait UserLookup {
+    fn user(&self, id: UserId) -> Option<User>;
+
+    /// The error tells why the user cannot be used as member
+    fn member(&self, user: &User) -> Result<Member, String>;
+}
+
+/// Looks users up in the mentions of the message first, then in the cache
+struct MessageLookup<'a> {
+    ctx: &'a Context,
+    msg: &'a Message,
+}
+
+impl UserLookup for MessageLookup<'_> {
+    fn user(&self, id: UserId) -> Option<User> {
+        if let Some(u) = self.msg.mentions.iter().find(|u| u.id == id) {
+            return Some(u.clone());
+        }
+        self.ctx.cache.read().user(id).map(|u| u.read().clone())
+    }
+
+    fn member(&self, user: &User) -> Result<Member, String> {
+        let guild_id = self.msg.guild_id.ok_or_else(|| "members can only be mentioned on servers".to_owned())?;
+        guild_id.member(self.ctx, user.id).map_err(|_| format!("{} is not a member of this server", user.name))
+    }
+}
+
+/// Parses the input (the message content without prefix and command key) according to the specs.
+/// Optional arguments are skipped if the remaining arguments would not match otherwise.
+/// Required image arguments are only skipped if the input does not match with them
+pub fn parse(specs: &[ArgumentSpec], input: &str, ctx: &Context, msg: &Message) -> Result<Args, ArgumentError> {
+    parse_with(specs, input, &MessageLookup { ctx, msg })
+}
+
+pub(crate) fn parse_with(specs: &[ArgumentSpec], input: &str, lookup: &dyn UserLookup) -> Result<Args, ArgumentError> {
+    let mut args = Args::default();
+    if let Err(e) = parse_from(specs, input, lookup, false, &mut args.values) {
+        args.values.clear();
+        parse_from(specs, input, lookup, true, &mut args.values).map_err(|_| e)?;
+    }
+    Ok(args)
+}
+
+fn parse_from(specs: &[ArgumentSpec], input: &str, lookup: &dyn UserLookup, skip_images: bool, values: &mut Vec<(&'static str, ArgumentValue)>) -> Result<(), ArgumentError> {
+    let input = input.trim_start();
+    let spec = match specs.first() {
+        Some(s) => s,
+        None => {
+            return if input.is_empty() { Ok(()) } else { Err(ArgumentError::TooMany(input.to_owned())) };
+        }
+    };
+    let optional = spec.optional || (skip_images && spec.kind == ArgumentKind::Image);
+
+    if input.is_empty() {
+        return if optional {
+            parse_from(&specs[1..], input, lookup, skip_images, values)
+        } else {
+            Err(ArgumentError::Missing(spec.name))
+        };
+    }
+
+    match parse_one(spec, input, lookup) {
+        Ok((value, rest)) => {
+            let len = values.len();
+            values.push((spec.name, value));
+            match parse_from(&specs[1..], rest, lookup, skip_images, values) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    values.truncate(len);
+                    if !optional {
+                        return Err(e);
+                    }
+                    // TRY AGAIN WITHOUT THE OPTIONAL ARGUMENT
+                    parse_from(&specs[1..], input, lookup, skip_images, values).map_err(|_| e)
+                }
+            }
+        }
+        Err(_) if optional => parse_from(&specs[1..], input, lookup, skip_images, values),
+        Err(e) => Err(e)
+    }
+}
+
+/// Returns the parsed value and the input which is left
+fn parse_one<'a>(spec: &ArgumentSpec, input: &'a str, lookup: &dyn UserLookup) -> Result<(ArgumentValue, &'a str), ArgumentError> {
+    match spec.kind {
+        ArgumentKind::User => {
+            let (token, rest) = next_token(input);
+            let user = parse_user(spec, token, lookup)?;
+            Ok((ArgumentValue::User(user), rest))
+        }
+        ArgumentKind::Member => {
+            let (token, rest) = next_token(input);
+            let user = parse_user(spec, token, lookup)?;
+            match lookup.member(&user) {
+                Ok(m) => Ok((ArgumentValue::Member(m), rest)),
+                Err(e) => Err(ArgumentError::Invalid(spec.name, e))
+            }
+        }
+        ArgumentKind::Integer => {
+            let (token, rest) = next_token(input);
+            match token.parse::<i64>() {
+                Ok(i) => Ok((ArgumentValue::Integer(i), rest)),
+                Err(_) => Err(ArgumentError::Invalid(spec.name, format!("``{}`` is not a number", token)))
+            }
+        }
+        ArgumentKind::Quoted => {
+            if let Some(quoted) = input.strip_prefix('"') {
+                match quoted.find('"') {
+                    Some(end) => Ok((ArgumentValue::Text(quoted[..end].to_owned()), &quoted[end + 1..])),
+                    None => Err(ArgumentError::Invalid(spec.name, "missing closing quote".to_owned()))
+                }
+            } else {
+                let (token, rest) = next_token(input);
+                Ok((ArgumentValue::Text(token.to_owned()), rest))
+            }
+        }
+        ArgumentKind::Rest => Ok((ArgumentValue::Text(input.trim_end().to_owned()), "")),
+        ArgumentKind::Image => {
+            let (token, rest) = next_token(input);
+            if let Some(image) = parse_image_link(token) {
+                return Ok((ArgumentValue::Image(image), rest));
+            }
+            match parse_user(spec, token, lookup) {
+                Ok(user) => Ok((ArgumentValue::Image(ImageArgument::User(user)), rest)),
+                Err(_) => Err(ArgumentError::Invalid(spec.name, "expected a mention of a user (@User), an image URL or a custom emoji".to_owned()))
+            }
+        }
+        ArgumentKind::Delimited => {
+            let (text, rest) = match input.find(',') {
+                Some(i) => (&input[..i], &input[i + 1..]),
+                None => (input, "")
+            };
+            let text = text.trim();
+            if text.is_empty() {
+                return Err(ArgumentError::Invalid(spec.name, "must not be empty".to_owned()));
+            }
+            Ok((ArgumentValue::Text(text.to_owned()), rest))
+        }
+        ArgumentKind::List => {
+            let list = split_list(input);
+            if list.is_empty() {
+                return Err(ArgumentError::Invalid(spec.name, "must not be empty".to_owned()));
+            }
+            Ok((ArgumentValue::List(list), ""))
+        }
+    }
+}
+
+/// The trimmed, non-empty parts between the commas
+pub(crate) fn split_list(input: &str) -> Vec<String> {
+    input.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned).collect()
+}
+
+/// A mention (<@id> or <@!id>) or a plain ID. IDs need at least 15 digits, so small numbers are not taken for users
+fn parse_user(spec: &ArgumentSpec, token: &str, lookup: &dyn UserLookup) -> Result<User, ArgumentError> {
+    lazy_static! {
+        static ref REG: regex::Regex = regex::Regex::new(r#"^(?:<@!?(?P<mention>[0-9]+)>|(?P<id>[0-9]{15,20}))$"#).expect("could not compile regex");
+    }
+    let id = REG.captures(token)
+        .and_then(|c| c.name("mention").or_else(|| c.name("id")))
+        .and_then(|id| id.as_str().parse::<u64>().ok());
+    let id = match id {
+        Some(id) => UserId(id),
+        None => return Err(ArgumentError::Invalid(spec.name, "expected a mention of a user (@User)".to_owned()))
+    };
+
+    match lookup.user(id) {
+        Some(u) => Ok(u),
+        None => Err(ArgumentError::Invalid(spec.name, "user not found".to_owned()))
+    }
+}
+
+/// An image URL or a custom emoji. URLs may be wrapped in <> to hide their embed
+pub(crate) fn parse_image_link(token: &str) -> Option<ImageArgument> {
+    lazy_static! {
+        static ref EMOJI: regex::Regex = regex::Regex::new(r#"^<(?P<animated>a?):\w+:(?P<id>[0-9]+)>$"#).expect("could not compile regex");
+    }
+    if let Some(c) = EMOJI.captures(token) {
+        let extension = if c["animated"].is_empty() { "png" } else { "gif" };
+        return Some(ImageArgument::Emoji(format!("https://cdn.discordapp.com/emojis/{}.{}", &c["id"], extension)));
+    }
+
+    let url = token.trim_start_matches('<').trim_end_matches('>');
+    match reqwest::Url::parse(url) {
+        Ok(u) if u.scheme() == "http" || u.scheme() == "https" => Some(ImageArgument::Url(url.to_owned())),
+        _ => None
+    }
+}
+
+/// Splits off the next whitespace delimited token
+fn next_token(input: &str) -> (&str, &str) {
+    match input.find(char::is_whitespace) {
+        Some(i) => (&input[..i], &input[i..]),
+        None => (input, "")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JANE: u64 = 53908232999999999;
+    const MASON: u64 = 53908232506183680;
+
+    const LOVE_SPECS: &[ArgumentSpec] = &[ArgumentSpec::optional("lover", ArgumentKind::User), ArgumentSpec::new("loved", ArgumentKind::User)];
+
+    /// Knows Jane and Mason. Nobody is a member
+    struct MockLookup;
+
+    impl UserLookup for MockLookup {
+        fn user(&self, id: UserId) -> Option<User> {
+            let name = match id.0 {
+                JANE => "Jane",
+                MASON => "Mason",
+                _ => return None
+            };
+            let json = format!(r#"{{"id": "{}", "username": "{}", "avatar": null, "discriminator": "0001"}}"#, id.0, name);
+            Some(serde_json::from_str(&json).expect("could not parse mock user"))
+        }
+
+        fn member(&self, _: &User) -> Result<Member, String> {
+            Err("members can only be mentioned on servers".to_owned())
+        }
+    }
+
+    fn parse(specs: &[ArgumentSpec], input: &str) -> Result<Args, ArgumentError> {
+        parse_with(specs, input, &MockLookup)
+    }
+
+    #[test]
+    fn skips_optional_arguments() {
+        let args = parse(LOVE_SPECS, &format!("<@{}>", JANE)).unwrap();
+        assert!(args.user("lover").is_none());
+        assert_eq!(args.user("loved").unwrap().name, "Jane");
+
+        let args = parse(LOVE_SPECS, &format!("<@{}> <@!{}>", MASON, JANE)).unwrap();
+        assert_eq!(args.user("lover").unwrap().name, "Mason");
+        assert_eq!(args.user("loved").unwrap().name, "Jane");
+
+        let specs = &[ArgumentSpec::optional("count", ArgumentKind::Integer), ArgumentSpec::new("text", ArgumentKind::Rest)];
+        let args = parse(specs, "3 little pigs").unwrap();
+        assert_eq!(args.integer("count"), Some(3));
+        assert_eq!(args.text("text"), Some("little pigs"));
+        let args = parse(specs, "little pigs").unwrap();
+        assert_eq!(args.integer("count"), None);
+        assert_eq!(args.text("text"), Some("little pigs"));
+    }
+
+    #[test]
+    fn missing_and_too_many_arguments() {
+        match parse(LOVE_SPECS, "") {
+            Err(ArgumentError::Missing("loved")) => {}
+            other => panic!("expected missing argument, got {:?}", other),
+        }
+        match parse(&[ArgumentSpec::new("count", ArgumentKind::Integer)], "1 2") {
+            Err(ArgumentError::TooMany(_)) => {}
+            other => panic!("expected too many arguments, got {:?}", other),
+        }
+        match parse(&[ArgumentSpec::new("count", ArgumentKind::Integer)], "one") {
+            Err(ArgumentError::Invalid("count", _)) => {}
+            other => panic!("expected invalid argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quoted_arguments() {
+        let specs = &[ArgumentSpec::new("top", ArgumentKind::Quoted), ArgumentSpec::new("bottom", ArgumentKind::Quoted)];
+        let args = parse(specs, r#""top text" bottom"#).unwrap();
+        assert_eq!(args.text("top"), Some("top text"));
+        assert_eq!(args.text("bottom"), Some("bottom"));
+
+        match parse(specs, r#""top text bottom"#) {
+            Err(ArgumentError::Invalid("top", _)) => {}
+            other => panic!("expected invalid argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mentions_and_ids() {
+        let specs = &[ArgumentSpec::new("user", ArgumentKind::User)];
+        for input in [format!("<@{}>", JANE), format!("<@!{}>", JANE), JANE.to_string()].iter() {
+            assert_eq!(parse(specs, input).unwrap().user("user").unwrap().name, "Jane", "input: {}", input);
+        }
+
+        // TOO SHORT FOR AN ID
+        match parse(specs, "123") {
+            Err(ArgumentError::Invalid("user", _)) => {}
+            other => panic!("expected invalid argument, got {:?}", other),
+        }
+        match parse(specs, "<@123>") {
+            Err(ArgumentError::Invalid("user", ref e)) if e == "user not found" => {}
+            other => panic!("expected unknown user, got {:?}", other),
+        }
+        match parse(&[ArgumentSpec::new("member", ArgumentKind::Member)], &format!("<@{}>", JANE)) {
+            Err(ArgumentError::Invalid("member", _)) => {}
+            other => panic!("expected invalid argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn delimited_and_list_arguments() {
+        let specs = &[ArgumentSpec::new("first", ArgumentKind::Delimited), ArgumentSpec::new("second", ArgumentKind::Delimited)];
+        let args = parse(specs, "Drake, the golden tests").unwrap();
+        assert_eq!(args.text("first"), Some("Drake"));
+        assert_eq!(args.text("second"), Some("the golden tests"));
+
+        let specs = &[ArgumentSpec::new("choices", ArgumentKind::List)];
+        let args = parse(specs, "pizza, pasta ,, salad").unwrap();
+        assert_eq!(args.list("choices").unwrap(), &["pizza".to_owned(), "pasta".to_owned(), "salad".to_owned()][..]);
+        match parse(specs, " , ") {
+            Err(ArgumentError::Invalid("choices", _)) => {}
+            other => panic!("expected invalid argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn image_arguments() {
+        let specs = &[ArgumentSpec::new("image", ArgumentKind::Image), ArgumentSpec::new("text", ArgumentKind::Rest)];
+        match parse(specs, "https://example.com/a.png hello").unwrap().image("image") {
+            Some(ImageArgument::Url(u)) => assert_eq!(u, "https://example.com/a.png"),
+            other => panic!("expected url, got {:?}", other),
+        }
+        match parse(specs, &format!("<@{}> hello", JANE)).unwrap().image("image") {
+            Some(ImageArgument::User(u)) => assert_eq!(u.name, "Jane"),
+            other => panic!("expected user, got {:?}", other),
+        }
+        // WITHOUT AN IMAGE THE AUTHOR'S AVATAR IS USED
+        let args = parse(specs, "hello").unwrap();
+        assert!(args.image("image").is_none());
+        assert_eq!(args.text("text"), Some("hello"));
+    }
+}
\ No newline at end of file
@@ -1,19 +1,32 @@
 use std::fmt;
 use std::fmt::Formatter;
+use std::time::Duration;
 
 use super::prelude::*;
 
 #[derive(Clone)]
 pub struct Command {
     pub key: &'static str,
+    pub aliases: &'static [&'static str],
     pub description: &'static str,
-    pub help_page: &'static str,
+    pub params: &'static [ArgumentSpec],
     pub category: Category,
+    pub cooldowns: &'static [Cooldown],
+    pub requirements: &'static Requirements,
+    /// The user is told that the command took too long if it does not finish in time
+    pub timeout: Duration,
     pub func: fn(args: CommandArguments) -> CommandResult,
 }
 
+impl Command {
+    /// The usage string generated from the argument specs. Empty if the command has no arguments
+    pub fn usage(&self) -> String {
+        super::arguments::usage(self.params)
+    }
+}
+
 impl fmt::Debug for Command {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, r##"Command {{ key = "{}", desc = "{}", help = "{}", cat = "{}" }}"##, self.key, self.description, self.help_page, self.category.to_string())
+        write!(f, r##"Command {{ key = "{}", desc = "{}", usage = "{}", cat = "{}" }}"##, self.key, self.description, self.usage(), self.category)
     }
 }
\ No newline at end of file
@@ -1,6 +1,10 @@
+use serenity::model::id::GuildId;
+
 use crate::util;
 use crate::util::eventwaiter::Eventwaiter;
+use crate::util::guild_settings::{GuildSettings, GuildSettingsManager};
 
+use super::Args;
 use super::prelude::*;
 
 /// This struct will be passed for every command
@@ -10,30 +14,44 @@ use super::prelude::*;
 /// m: The message without the prefix
 ///
 /// handler: The handler wrapped in a already cloned Arc
+///
+/// guild: The guild the command was used in. None in direct messages
+///
+/// params: The parsed arguments of the command
+///
+/// prefix: The prefix of the current guild. Use this instead of the default prefix when showing commands to the user
+///
+/// image: The templates. Clone the inner Arc once, the templates may be reloaded while the command runs
 #[derive(Clone)]
 pub struct CommandArguments<'a> {
     pub ctx: &'a Context,
     pub m: &'a Message,
     pub handler: Arc<RwLock<CommandManager>>,
     pub safe: Arc<RwLock<Safe>>,
-    pub image: Arc<util::image::ImageStorage>,
+    pub image: Arc<RwLock<Arc<util::image::ImageStorage>>>,
     pub settings: Arc<StaticSettings>,
     pub command: &'a Command,
-    pub event_waiter: Arc<Eventwaiter>
+    pub event_waiter: Arc<Eventwaiter>,
+    pub guild_settings: Arc<GuildSettingsManager>,
+    pub guild: Option<GuildContext>,
+    pub params: Args,
+    pub prefix: String,
 }
 
+/// The guild a command was used in
+#[derive(Clone, Debug)]
+pub struct GuildContext {
+    pub id: GuildId,
+    pub settings: GuildSettings,
+}
 
 impl<'a> CommandArguments<'a> {
-    pub fn new(ctx: &'a Context, m: &'a Message, handler: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, image: Arc<util::image::ImageStorage>, settings: Arc<StaticSettings>, command: &'a Command, event_waiter: Arc<Eventwaiter>) -> CommandArguments<'a> {
-        CommandArguments {
-            ctx,
-            m,
-            handler,
-            safe,
-            image,
-            settings,
-            command,
-            event_waiter,
-        }
+    pub fn guild_id(&self) -> Option<GuildId> {
+        self.guild.as_ref().map(|g| g.id)
     }
-}
+
+    /// For commands with guild_only. The handler checks it before the command runs, this only fails if a command forgot the requirement
+    pub fn require_guild(&self) -> Result<&GuildContext, CommandError> {
+        self.guild.as_ref().ok_or_else(|| CommandError::bad_input("This command can only be used on servers"))
+    }
+}
\ No newline at end of file
@@ -1,5 +1,11 @@
-use crate::command_framework::CommandAction;
+use serenity::model::id::UserId;
+
+use crate::command_framework::{arguments, requirements};
+use crate::command_framework::{Args, CommandAction, GuildContext};
+use crate::command_framework::arguments::ArgumentError;
 use crate::Handler;
+use crate::interactions;
+use crate::util::analytics::Outcome;
 
 use super::prelude::*;
 
@@ -7,19 +13,34 @@ pub(crate) fn handle_command(handler: &Handler, ctx: Context, msg: Message) {
     if msg.author.bot {
         return;
     }
-    if msg.is_private() {
-        return;
-    }
 
     info!("[Message] {}: {}", msg.author.name, msg.content_safe(&ctx.cache));
-    //TODO: Server config prefix
-    if !msg.content.starts_with(&handler.settings.default_prefix) {
-        return;
-    }
+    // NONE IN DIRECT MESSAGES
+    let guild = msg.guild_id.map(|id| GuildContext {
+        id,
+        settings: handler.guild_settings.get(id),
+    });
+    let prefix = match guild {
+        Some(ref g) => g.settings.prefix_or(&handler.settings.default_prefix).to_owned(),
+        None => handler.settings.default_prefix.clone()
+    };
+    let bot_id = ctx.cache.read().user.id;
+
+    let content = match strip_prefix(&msg.content, &prefix, bot_id) {
+        Some(s) => s.trim_start().to_owned(),
+        None => return
+    };
 
     let mut msg = msg.clone();
-    msg.content = msg.content[1..].to_string();
+    msg.content = content;
+    if !msg.content.contains(&bot_id.0.to_string()) {
+        // THE BOT WAS ONLY MENTIONED AS PREFIX
+        msg.mentions.retain(|u| u.id != bot_id);
+    }
     let msg_split: Vec<&str> = msg.content.split_whitespace().collect();
+    if msg_split.is_empty() {
+        return;
+    }
 
     let cmd;
     {
@@ -27,44 +48,183 @@ pub(crate) fn handle_command(handler: &Handler, ctx: Context, msg: Message) {
         let command_manager = command_manager_arc.read();
         match command_manager.get_command(msg_split[0]) {
             Some(c) => cmd = c.clone(),
-            None => return
+            None => {
+                let suggestions = command_manager.suggest(msg_split[0], 3);
+                if !suggestions.is_empty() {
+                    let suggestions: Vec<String> = suggestions.into_iter().map(|s| format!("``{}{}``", &prefix, s)).collect();
+                    let _ = msg.reply(&ctx, format!("Unknown command! Did you mean {}?", suggestions.join(" or ")));
+                }
+                return;
+            }
         }
     } // DROP READ LOCK
-    {
-        let args = CommandArguments::new(&ctx,
-                                         &msg,
-                                         Arc::clone(&handler.ch),
-                                         Arc::clone(&handler.safe),
-                                         Arc::clone(&handler.image),
-                                         Arc::clone(&handler.settings),
-                                         &cmd,
-                                         Arc::clone(&handler.eventwaiter));
-        match (cmd.func)(args) {
-            Ok(action) => {
-                match action {
-                    CommandAction::MarkAsSucceeded => {
-                        let _ = msg.react(&ctx, ReactionType::from("✅"));
-                    }
-                    CommandAction::MarkAsFailed => {
-                        let _ = msg.react(&ctx, ReactionType::from("❌"));
-                    }
-                    CommandAction::MarkAsWrongUsage => {
-                        let _ = msg.react(&ctx, ReactionType::from("❔"));
-                    }
-                    CommandAction::PrintUsage => {
-                        let _ = msg.react(&ctx, ReactionType::from("❔"));
-                        let _ = msg.channel_id.send_message(&ctx, |eb| {
-                            eb.content(format!("Invalid syntax! Try: ``{}{} {}``", &handler.settings.default_prefix, cmd.key, cmd.help_page));
-                            eb
-                        });
-                    }
+
+    if !check_requirements(handler, &ctx, &msg, &cmd, guild.as_ref()) {
+        return;
+    }
+
+    let params = match arguments::parse(cmd.params, &msg.content[msg_split[0].len()..], &ctx, &msg) {
+        Ok(k) => k,
+        Err(e) => {
+            report_argument_error(handler, &ctx, &msg, &cmd, &prefix, e);
+            return;
+        }
+    };
+    execute(handler, &ctx, &msg, &cmd, guild, params, prefix);
+}
+
+/// Tells the user why they are not allowed to use the command. Returns true if all requirements are met
+pub(crate) fn check_requirements(handler: &Handler, ctx: &Context, msg: &Message, cmd: &Command, guild: Option<&GuildContext>) -> bool {
+    match requirements::check(cmd, ctx, msg, &handler.settings.owners, guild.map(|g| &g.settings)) {
+        Ok(()) => true,
+        Err(denial) => {
+            handler.analytics.record(cmd, msg, Outcome::Denied, None);
+            react(ctx, msg, "🚫");
+            let _ = msg.reply(ctx, format!("Sorry! {}", denial));
+            false
+        }
+    }
+}
+
+pub(crate) fn report_argument_error(handler: &Handler, ctx: &Context, msg: &Message, cmd: &Command, prefix: &str, e: ArgumentError) {
+    handler.analytics.record(cmd, msg, Outcome::InvalidArguments, None);
+    react(ctx, msg, "❔");
+    let _ = msg.channel_id.send_message(ctx, |eb| {
+        eb.content(format!("{}! Try: ``{}``", e, usage_line(prefix, cmd)));
+        eb
+    });
+}
+
+/// Queues the command on the executor if it is not on cooldown
+pub(crate) fn execute(handler: &Handler, ctx: &Context, msg: &Message, cmd: &Command, guild: Option<GuildContext>, params: Args, prefix: String) {
+    if let Some(remaining) = handler.cooldowns.hit(cmd, msg) {
+        handler.analytics.record(cmd, msg, Outcome::OnCooldown, None);
+        report(ctx, msg, cmd, &prefix, Ok(CommandAction::OnCooldown(remaining)));
+        return;
+    }
+
+    let job = {
+        let handler = handler.clone();
+        let ctx = ctx.clone();
+        let msg = msg.clone();
+        let cmd = cmd.clone();
+        let prefix = prefix.clone();
+        move || {
+            let args = CommandArguments {
+                ctx: &ctx,
+                m: &msg,
+                handler: Arc::clone(&handler.ch),
+                safe: Arc::clone(&handler.safe),
+                image: Arc::clone(&handler.image),
+                settings: Arc::clone(&handler.settings),
+                command: &cmd,
+                event_waiter: Arc::clone(&handler.eventwaiter),
+                guild_settings: Arc::clone(&handler.guild_settings),
+                guild,
+                params,
+                prefix,
+            };
+            (cmd.func)(args)
+        }
+    };
+    if handler.executor.spawn(ctx, msg, cmd, &prefix, job).is_err() {
+        react(ctx, msg, "❌");
+        let _ = msg.reply(ctx, "I'm very busy right now. Please try again in a few seconds!");
+    }
+}
+
+/// Tells the user how the command went
+pub(crate) fn report(ctx: &Context, msg: &Message, cmd: &Command, prefix: &str, result: CommandResult) {
+    match result {
+        Ok(action) => {
+            match action {
+                CommandAction::MarkAsSucceeded => {
+                    react(ctx, msg, "✅");
+                }
+                CommandAction::MarkAsFailed => {
+                    react(ctx, msg, "❌");
+                }
+                CommandAction::PrintUsage => {
+                    react(ctx, msg, "❔");
+                    let _ = msg.channel_id.send_message(ctx, |eb| {
+                        eb.content(format!("Invalid syntax! Try: ``{}``", usage_line(prefix, cmd)));
+                        eb
+                    });
+                }
+                CommandAction::OnCooldown(remaining) => {
+                    react(ctx, msg, "⏳");
+                    let seconds = remaining.as_secs() + if remaining.subsec_nanos() > 0 { 1 } else { 0 };
+                    let _ = msg.reply(ctx, format!("Slow down! You can use ``{}{}`` again in {} second(s)", prefix, cmd.key, seconds));
+                }
+                CommandAction::TimedOut => {
+                    react(ctx, msg, "⌛");
+                    let _ = msg.reply(ctx, format!("Sorry! ``{}{}`` took too long. Please try again later!", prefix, cmd.key));
                 }
-            }
-            Err(err) => {
-                let _ = msg.react(&ctx, ReactionType::from("❌"));
-                let _ = msg.reply(&ctx, "I'm sorry, I failed... There was an error executing the command. Please try again later!");
-                error!("Could not execute command: {:#?}", err);
             }
         }
+        Err(err) => report_error(ctx, msg, cmd, err)
     }
+}
+
+/// Logs the error with a short id the user can tell us and tells the user what went wrong
+/// The user can fix bad input and missing cache themselves, so these replies and their log lines have no error ID
+fn report_error(ctx: &Context, msg: &Message, cmd: &Command, err: CommandError) {
+    let id = error_id();
+    let reply = match err {
+        CommandError::BadInput { ref message, .. } => {
+            warn!(r#"command "{}" failed: {}"#, cmd.key, err);
+            react(ctx, msg, "❔");
+            message.clone()
+        }
+        CommandError::MissingCache { ref message, .. } => {
+            warn!(r#"command "{}" failed: {}"#, cmd.key, err);
+            react(ctx, msg, "⏳");
+            message.clone()
+        }
+        CommandError::UpstreamUnavailable { ref message, .. } => {
+            warn!(r#"[{}] command "{}" failed: {}"#, id, cmd.key, err);
+            react(ctx, msg, "❌");
+            format!("Sorry, {}... Please try again later! (Error ID: ``{}``)", message, id)
+        }
+        CommandError::DiscordApi { ref message, .. } => {
+            error!(r#"[{}] command "{}" failed: {}"#, id, cmd.key, err);
+            react(ctx, msg, "❌");
+            format!("Sorry, Discord did not let me do that: {}. Please try again later! (Error ID: ``{}``)", message, id)
+        }
+        CommandError::Internal { ref message, .. } => {
+            error!(r#"[{}] command "{}" failed: {}"#, id, cmd.key, err);
+            react(ctx, msg, "❌");
+            format!("I'm sorry, I failed... {}. Please try again later! (Error ID: ``{}``)", message, id)
+        }
+    };
+    let _ = msg.reply(ctx, reply);
+}
+
+/// Messages built out of slash commands get the reaction on the response message
+fn react(ctx: &Context, msg: &Message, emoji: &str) {
+    let _ = msg.channel_id.create_reaction(ctx, interactions::reaction_target(msg), ReactionType::from(emoji));
+}
+
+/// Six hex digits. Short enough to be quoted by users, long enough to be found in the log
+fn error_id() -> String {
+    format!("{:06x}", rand::random::<u32>() & 0x00FF_FFFF)
+}
+
+/// Returns the message's content without the prefix or None if the message does not start with the prefix.
+/// Mentioning the bot works as a prefix, too (e.g. "@Bot help")
+fn strip_prefix<'a>(content: &'a str, prefix: &str, bot_id: UserId) -> Option<&'a str> {
+    if let Some(s) = content.strip_prefix(prefix) {
+        return Some(s);
+    }
+
+    for mention in [format!("<@{}>", bot_id.0), format!("<@!{}>", bot_id.0)].iter() {
+        if let Some(s) = content.strip_prefix(mention.as_str()) {
+            return Some(s);
+        }
+    }
+    None
+}
+
+fn usage_line(prefix: &str, cmd: &Command) -> String {
+    format!("{}{} {}", prefix, cmd.key, cmd.usage()).trim_end().to_owned()
 }
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serenity::prelude::Mutex;
+
+use super::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CooldownBucket {
+    User,
+    Channel,
+    Guild,
+    Global,
+}
+
+impl CooldownBucket {
+    fn id(self, msg: &Message) -> u64 {
+        match self {
+            Self::User => msg.author.id.0,
+            Self::Channel => msg.channel_id.0,
+            Self::Guild => msg.guild_id.map(|g| g.0).unwrap_or(msg.channel_id.0),
+            Self::Global => 0,
+        }
+    }
+}
+
+/// A command may only be used `uses` times every `seconds` seconds per bucket
+#[derive(Clone, Debug)]
+pub struct Cooldown {
+    pub bucket: CooldownBucket,
+    pub uses: u32,
+    pub seconds: u64,
+}
+
+impl Cooldown {
+    pub const fn new(bucket: CooldownBucket, uses: u32, seconds: u64) -> Self {
+        Cooldown {
+            bucket,
+            uses,
+            seconds,
+        }
+    }
+}
+
+struct Bucket {
+    window: Duration,
+    uses: VecDeque<Instant>,
+}
+
+impl Bucket {
+    fn prune(&mut self, now: Instant) {
+        while let Some(first) = self.uses.front() {
+            if now.duration_since(*first) < self.window {
+                break;
+            }
+            self.uses.pop_front();
+        }
+    }
+}
+
+pub struct CooldownManager {
+    // (COMMAND KEY, INDEX OF COOLDOWN, BUCKET ID) => USES
+    buckets: Mutex<HashMap<(&'static str, usize, u64), Bucket>>
+}
+
+impl CooldownManager {
+    pub fn new() -> Self {
+        CooldownManager {
+            buckets: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Records a use of the command. If one of the command's cooldowns is exhausted nothing is recorded
+    /// and the time until the command can be used again is returned
+    pub fn hit(&self, cmd: &Command, msg: &Message) -> Option<Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+
+        let mut remaining: Option<Duration> = None;
+        for (i, cooldown) in cmd.cooldowns.iter().enumerate() {
+            let bucket = match buckets.get_mut(&(cmd.key, i, cooldown.bucket.id(msg))) {
+                Some(s) => s,
+                None => continue
+            };
+            bucket.prune(now);
+
+            if bucket.uses.len() >= cooldown.uses as usize {
+                let wait = bucket.window - now.duration_since(*bucket.uses.front().unwrap());
+                remaining = Some(remaining.map_or(wait, |r| r.max(wait)));
+            }
+        }
+        if remaining.is_some() {
+            return remaining;
+        }
+
+        for (i, cooldown) in cmd.cooldowns.iter().enumerate() {
+            buckets.entry((cmd.key, i, cooldown.bucket.id(msg)))
+                .or_insert_with(|| Bucket {
+                    window: Duration::from_secs(cooldown.seconds),
+                    uses: VecDeque::new(),
+                })
+                .uses.push_back(now);
+        }
+        None
+    }
+
+    /// Removes all buckets whose uses are expired
+    pub fn clean(&self) {
+        let now = Instant::now();
+        self.buckets.lock().retain(|_, b| {
+            b.prune(now);
+            !b.uses.is_empty()
+        });
+    }
+}
\ No newline at end of file
@@ -1,61 +1,134 @@
 use std::{error, fmt};
 use std::fmt::Formatter;
-
-use super::prelude::*;
+use std::time::Duration;
 
 pub type CommandResult = Result<CommandAction, CommandError>;
 
 pub enum CommandAction {
     MarkAsSucceeded,
     MarkAsFailed,
-    MarkAsWrongUsage,
     PrintUsage,
+    // Remaining time until the command can be used again
+    OnCooldown(Duration),
+    // The command exceeded its timeout. Its result will be discarded
+    TimedOut,
+}
+
+/// Everything that can go wrong while executing a command. The message is shown to the user,
+/// the source is only written to the log
+#[derive(Debug)]
+pub enum CommandError {
+    // An external service (e.g. urban dictionary) is not reachable or answered garbage
+    UpstreamUnavailable { message: String, source: Option<Box<dyn error::Error>> },
+    // The input of the user cannot be processed
+    BadInput { message: String, source: Option<Box<dyn error::Error>> },
+    // A cache which is filled by a schedule is still empty
+    MissingCache { message: String, source: Option<Box<dyn error::Error>> },
+    // A request to discord failed
+    DiscordApi { message: String, source: Option<Box<dyn error::Error>> },
+    // Everything else. Most likely a bug
+    Internal { message: String, source: Option<Box<dyn error::Error>> },
+}
+
+/// The kind of a CommandError without its message and source
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    UpstreamUnavailable,
+    BadInput,
+    MissingCache,
+    DiscordApi,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UpstreamUnavailable => "upstream unavailable",
+            Self::BadInput => "bad input",
+            Self::MissingCache => "missing cache",
+            Self::DiscordApi => "discord api",
+            Self::Internal => "internal",
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct CommandError {
-    pub cmd: Command,
-    pub err: String,
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
 }
 
-#[allow(dead_code)]
 impl CommandError {
-    pub fn new_str(cmd: &Command, err: &str) -> CommandError {
-        CommandError {
-            cmd: cmd.clone(),
-            err: err.to_owned(),
+    pub fn bad_input(message: &str) -> CommandError {
+        CommandError::BadInput {
+            message: message.to_owned(),
+            source: None,
         }
     }
-    pub fn new(cmd: &Command, err: String) -> CommandError {
-        CommandError {
-            cmd: cmd.clone(),
-            err,
+
+    pub fn missing_cache(message: &str) -> CommandError {
+        CommandError::MissingCache {
+            message: message.to_owned(),
+            source: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Self::UpstreamUnavailable { .. } => ErrorKind::UpstreamUnavailable,
+            Self::BadInput { .. } => ErrorKind::BadInput,
+            Self::MissingCache { .. } => ErrorKind::MissingCache,
+            Self::DiscordApi { .. } => ErrorKind::DiscordApi,
+            Self::Internal { .. } => ErrorKind::Internal,
+        }
+    }
+
+    /// The message which is shown to the user
+    pub fn message(&self) -> &str {
+        match *self {
+            Self::UpstreamUnavailable { ref message, .. }
+            | Self::BadInput { ref message, .. }
+            | Self::MissingCache { ref message, .. }
+            | Self::DiscordApi { ref message, .. }
+            | Self::Internal { ref message, .. } => message
+        }
+    }
+
+    fn source_ref(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::UpstreamUnavailable { ref source, .. }
+            | Self::BadInput { ref source, .. }
+            | Self::MissingCache { ref source, .. }
+            | Self::DiscordApi { ref source, .. }
+            | Self::Internal { ref source, .. } => source.as_ref().map(|s| s.as_ref())
         }
     }
 }
 
 impl fmt::Display for CommandError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, r#"error in command "{}": {}"#, self.cmd.key, self.err)
+        match self.source_ref() {
+            Some(source) => write!(f, "{}: {}: {}", self.kind(), self.message(), source),
+            None => write!(f, "{}: {}", self.kind(), self.message()),
+        }
     }
 }
 
-
 impl error::Error for CommandError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
+        self.source_ref()
     }
 }
 
+/// Returns the value or returns the error from the command as the given kind of CommandError
+/// (e.g. unwrap_cmd_err!(UpstreamUnavailable, reqwest::get(url), "the cat service is not reachable right now"))
 macro_rules! unwrap_cmd_err {
-    ($cmd:expr, $func:expr, $extra:expr) => {
+    ($kind:ident, $func:expr, $message:expr) => {
     {
-        #[allow(unused_imports)]
-        use std::error::Error;
         use crate::command_framework::CommandError;
         match $func {
             Ok(o) => o,
-            Err(err) => return Err(CommandError::new($cmd, format!("{}: {}", $extra, err.to_string())))
+            Err(err) => return Err(CommandError::$kind { message: ($message).to_string(), source: Some(err.into()) })
         }
     }
     };
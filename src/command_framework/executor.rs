@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::Weak;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serenity::prelude::Mutex;
+use threadpool::ThreadPool;
+
+use crate::util::analytics::{Analytics, Outcome};
+
+use super::command_handler;
+use super::CommandAction;
+use super::prelude::*;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+// COMMANDS WHICH RUN LONGER THAN THIS GET A TYPING INDICATOR
+const TYPING_AFTER: Duration = Duration::from_millis(1500);
+// DISCORD SHOWS THE INDICATOR FOR 10 SECONDS
+const TYPING_INTERVAL: Duration = Duration::from_secs(8);
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(500);
+
+struct Running {
+    ctx: Context,
+    msg: Message,
+    cmd: Command,
+    prefix: String,
+    queued: Instant,
+    // NONE WHILE THE JOB WAITS FOR A WORKER
+    started: Option<Instant>,
+    last_typing: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct ExecutorBusy;
+
+/// Runs commands on a bounded pool of worker threads so blocking commands do not block the event threads.
+/// A watchdog thread shows a typing indicator for long running commands and tells the user when a command exceeds its timeout
+pub struct Executor {
+    pool: Mutex<ThreadPool>,
+    max_queued: usize,
+    running: Mutex<HashMap<u64, Running>>,
+    next_id: AtomicU64,
+    analytics: Arc<Analytics>,
+}
+
+impl Executor {
+    pub fn new(workers: usize, max_queued: usize, analytics: Arc<Analytics>) -> Arc<Self> {
+        let executor = Arc::new(Executor {
+            pool: Mutex::new(ThreadPool::with_name("command worker".to_owned(), workers)),
+            max_queued,
+            running: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            analytics,
+        });
+
+        let weak = Arc::downgrade(&executor);
+        std::thread::Builder::new()
+            .name("command watchdog".to_owned())
+            .spawn(move || watchdog(weak))
+            .expect("could not start command watchdog");
+        executor
+    }
+
+    /// Queues the job. Its result is reported to the user unless the command exceeded its timeout.
+    /// The timeout starts when a worker picks the job up. Jobs which wait longer than their timeout in the queue are dropped.
+    /// A job which times out while running cannot be stopped, so messages it sends itself (e.g. replies) still show up after the timeout message.
+    /// Fails if too many jobs are waiting already
+    pub fn spawn<F>(self: &Arc<Self>, ctx: &Context, msg: &Message, cmd: &Command, prefix: &str, job: F) -> Result<(), ExecutorBusy>
+        where F: FnOnce() -> CommandResult + Send + 'static {
+        let pool = self.pool.lock();
+        if pool.queued_count() >= self.max_queued {
+            return Err(ExecutorBusy);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.running.lock().insert(id, Running {
+            ctx: ctx.clone(),
+            msg: msg.clone(),
+            cmd: cmd.clone(),
+            prefix: prefix.to_owned(),
+            queued: Instant::now(),
+            started: None,
+            last_typing: None,
+        });
+
+        let executor = Arc::clone(self);
+        let key = cmd.key;
+        pool.execute(move || {
+            // THE WATCHDOG ALREADY REMOVED THE COMMAND IF IT WAITED TOO LONG IN THE QUEUE
+            match executor.running.lock().get_mut(&id) {
+                Some(r) => r.started = Some(Instant::now()),
+                None => return
+            }
+            let result = job();
+            // THE WATCHDOG ALREADY REMOVED THE COMMAND IF IT TOOK TOO LONG
+            let running = executor.running.lock().remove(&id);
+            match running {
+                Some(r) => {
+                    executor.analytics.record(&r.cmd, &r.msg, Outcome::of(&result), r.started.map(|s| s.elapsed()));
+                    command_handler::report(&r.ctx, &r.msg, &r.cmd, &r.prefix, result)
+                }
+                None => {
+                    if let Err(err) = result {
+                        error!(r#"Timed out command "{}" failed, too: {}"#, key, err);
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Runs a job which is not a command itself (e.g. answering a slash command) on the workers. It has no timeout.
+    /// Fails if too many jobs are waiting already
+    pub fn spawn_task<F>(&self, job: F) -> Result<(), ExecutorBusy> where F: FnOnce() + Send + 'static {
+        let pool = self.pool.lock();
+        if pool.queued_count() >= self.max_queued {
+            return Err(ExecutorBusy);
+        }
+        pool.execute(job);
+        Ok(())
+    }
+
+    fn check_running(&self) {
+        let now = Instant::now();
+        let mut timed_out = Vec::new();
+        let mut typing = Vec::new();
+        {
+            let mut running = self.running.lock();
+            let expired: Vec<u64> = running.iter()
+                .filter(|(_, r)| now.duration_since(r.started.unwrap_or(r.queued)) >= r.cmd.timeout)
+                .map(|(id, _)| *id)
+                .collect();
+            for id in expired {
+                timed_out.extend(running.remove(&id));
+            }
+
+            for r in running.values_mut() {
+                let due = match r.last_typing {
+                    Some(t) => now.duration_since(t) >= TYPING_INTERVAL,
+                    None => now.duration_since(r.queued) >= TYPING_AFTER
+                };
+                if due {
+                    r.last_typing = Some(now);
+                    typing.push((r.ctx.clone(), r.msg.channel_id));
+                }
+            }
+        } // DROP LOCK
+
+        for (ctx, channel_id) in typing {
+            let _ = channel_id.broadcast_typing(&ctx);
+        }
+        for r in timed_out {
+            match r.started {
+                Some(_) => warn!(r#"EXECUTOR: command "{}" exceeded its timeout of {}s"#, r.cmd.key, r.cmd.timeout.as_secs()),
+                None => warn!(r#"EXECUTOR: command "{}" waited longer than its timeout of {}s for a worker"#, r.cmd.key, r.cmd.timeout.as_secs())
+            }
+            self.analytics.record(&r.cmd, &r.msg, Outcome::TimedOut, r.started.map(|s| now.duration_since(s)));
+            command_handler::report(&r.ctx, &r.msg, &r.cmd, &r.prefix, Ok(CommandAction::TimedOut));
+        }
+    }
+}
+
+fn watchdog(executor: Weak<Executor>) {
+    loop {
+        std::thread::sleep(WATCHDOG_INTERVAL);
+        match executor.upgrade() {
+            Some(e) => e.check_running(),
+            None => return
+        }
+    }
+}
\ No newline at end of file
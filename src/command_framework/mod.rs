@@ -1,38 +1,158 @@
+use std::collections::HashMap;
+
+pub use arguments::{ArgumentKind, ArgumentSpec, Args, ImageArgument};
 pub use command::*;
 pub use command_args::*;
+pub use cooldown::{Cooldown, CooldownBucket, CooldownManager};
 pub use error::*;
+pub use executor::{DEFAULT_TIMEOUT, Executor};
+pub use requirements::Requirements;
 
 #[macro_use]
 mod error;
 mod command;
+pub mod arguments;
 mod command_args;
+mod cooldown;
+mod executor;
+pub mod requirements;
 pub mod command_handler;
 pub mod prelude;
 
 pub struct CommandManager {
-    commands: Vec<Command>
+    commands: Vec<Command>,
+    // LOWERCASE KEY OR ALIAS => INDEX IN COMMANDS
+    index: HashMap<String, usize>,
 }
 
 impl CommandManager {
     pub fn new() -> CommandManager {
         CommandManager {
             commands: vec![],
+            index: HashMap::new(),
         }
     }
 
+    /// Finds a command by its key or one of its aliases (case-insensitive)
     pub fn get_command(&self, k: &str) -> Option<&Command> {
-        if let Some(command) = self.commands.iter().find(|c| c.key.to_lowercase() == k.to_lowercase()) {
-            return Some(command);
-        } else {
-            return None;
-        }
+        self.index.get(&k.to_lowercase()).map(|i| &self.commands[*i])
     }
 
     pub fn register_command(&mut self, cmd: Command) {
+        let i = self.commands.len();
+        for name in std::iter::once(&cmd.key).chain(cmd.aliases.iter()) {
+            if let Some(other) = self.index.insert(name.to_lowercase(), i) {
+                warn!(r#"COMMAND MANAGER: "{}" of command "{}" overrides command "{}""#, name, cmd.key, self.commands[other].key);
+            }
+        }
         self.commands.push(cmd);
     }
 
+    /// Removes every command the predicate matches. The other commands keep their order
+    pub fn unregister_commands<F: Fn(&Command) -> bool>(&mut self, f: F) {
+        let commands = std::mem::take(&mut self.commands);
+        self.index.clear();
+        for cmd in commands.into_iter().filter(|c| !f(c)) {
+            self.register_command(cmd);
+        }
+    }
+
     pub fn get_all_commands(&self) -> &Vec<Command> {
         &self.commands
     }
+
+    /// Returns up to `max` command keys and aliases which are the closest to `k` by edit distance.
+    /// Only names which are at most a third of their length away are considered
+    pub fn suggest(&self, k: &str, max: usize) -> Vec<&str> {
+        let k = k.to_lowercase();
+        let mut candidates: Vec<(usize, &str)> = self.index.keys()
+            .map(|name| (levenshtein(&k, name), name.as_str()))
+            .filter(|(distance, name)| *distance <= std::cmp::max(1, name.chars().count() / 3))
+            .collect();
+        candidates.sort();
+        candidates.into_iter().take(max).map(|(_, name)| name).collect()
+    }
 }
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut last = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = if ca == *cb { last } else { last + 1 };
+            last = row[j + 1];
+            row[j + 1] = std::cmp::min(substitution, std::cmp::min(row[j], row[j + 1]) + 1);
+        }
+    }
+    row[b.len()]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::prelude::*;
+
+    use super::*;
+
+    fn command(key: &'static str, aliases: &'static [&'static str]) -> Command {
+        Command {
+            key,
+            aliases,
+            description: "",
+            params: &[],
+            category: Category::Misc,
+            cooldowns: &[],
+            requirements: &Requirements::NONE,
+            timeout: DEFAULT_TIMEOUT,
+            func: |_| Ok(MarkAsSucceeded),
+        }
+    }
+
+    fn manager() -> CommandManager {
+        let mut manager = CommandManager::new();
+        manager.register_command(command("meme", &[]));
+        manager.register_command(command("cmm", &["changemymind"]));
+        manager.register_command(command("drake", &[]));
+        manager.register_command(command("reloadtemplates", &[]));
+        manager
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("meme", "meme"), 0);
+        assert_eq!(levenshtein("drkae", "drake"), 2);
+    }
+
+    #[test]
+    fn suggests_aliases() {
+        assert_eq!(manager().suggest("changemymnd", 3), vec!["changemymind"]);
+        assert_eq!(manager().suggest("ChangeMyMnd", 3), vec!["changemymind"]);
+    }
+
+    #[test]
+    fn distance_cutoff() {
+        let manager = manager();
+        // SHORT NAMES STILL ALLOW ONE TYPO
+        assert_eq!(manager.suggest("mem", 3), vec!["meme"]);
+        assert!(manager.suggest("me", 3).is_empty());
+        // 5 CHARS: AT MOST 1
+        assert_eq!(manager.suggest("drak", 3), vec!["drake"]);
+        assert!(manager.suggest("drkae", 3).is_empty());
+        // 15 CHARS: AT MOST 5
+        assert_eq!(manager.suggest("reloadtmplts", 3), vec!["reloadtemplates"]);
+        assert!(manager.suggest("reloadtmp", 3).is_empty());
+    }
+
+    #[test]
+    fn closest_first() {
+        let mut manager = manager();
+        manager.register_command(command("changemyname", &[]));
+        assert_eq!(manager.suggest("changemymnd", 3), vec!["changemymind", "changemyname"]);
+        assert_eq!(manager.suggest("changemymnd", 1), vec!["changemymind"]);
+    }
+}
\ No newline at end of file
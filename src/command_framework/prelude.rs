@@ -3,14 +3,19 @@ pub use std::sync::Arc;
 pub use serenity::client::Context;
 pub use serenity::model::channel::Message;
 pub use serenity::model::channel::ReactionType;
+pub use serenity::model::permissions::Permissions;
 pub use serenity::prelude::RwLock;
 
+pub use crate::command_framework::{ArgumentKind, ArgumentSpec};
 pub use crate::command_framework::Command;
-pub use crate::command_framework::CommandAction::{MarkAsFailed, MarkAsSucceeded, MarkAsWrongUsage, PrintUsage};
+pub use crate::command_framework::CommandAction::{MarkAsFailed, MarkAsSucceeded, PrintUsage};
 pub use crate::command_framework::CommandArguments;
 pub use crate::command_framework::CommandError;
 pub use crate::command_framework::CommandManager;
 pub use crate::command_framework::CommandResult;
+pub use crate::command_framework::{Cooldown, CooldownBucket};
+pub use crate::command_framework::DEFAULT_TIMEOUT;
+pub use crate::command_framework::Requirements;
 pub use crate::commands::category::Category;
 pub use crate::StaticSettings;
 pub use crate::util::safe::Safe;
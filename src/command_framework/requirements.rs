@@ -0,0 +1,347 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use serenity::model::channel::Channel;
+use serenity::model::id::UserId;
+use serenity::model::permissions::Permissions;
+
+use crate::util::guild_settings::GuildSettings;
+
+use super::prelude::*;
+
+/// Requirements which have to be met before a command gets executed. Checked by the command handler
+#[derive(Clone, Debug)]
+pub struct Requirements {
+    /// Permissions the author needs in the channel
+    pub user_permissions: Permissions,
+    /// Permissions the bot needs in the channel
+    pub bot_permissions: Permissions,
+    pub nsfw_only: bool,
+    pub owner_only: bool,
+    /// Commands which are not guild only can be used in direct messages, too
+    pub guild_only: bool,
+}
+
+impl Requirements {
+    pub const NONE: Requirements = Requirements {
+        user_permissions: Permissions::empty(),
+        bot_permissions: Permissions::empty(),
+        nsfw_only: false,
+        owner_only: false,
+        guild_only: false,
+    };
+
+    pub const fn bot_permissions(bot_permissions: Permissions) -> Self {
+        Requirements {
+            bot_permissions,
+            ..Self::NONE
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Denial {
+    GuildOnly,
+    OwnerOnly,
+    Disabled,
+    NsfwOnly,
+    // Missing permissions
+    UserPermissions(Permissions),
+    BotPermissions(Permissions),
+}
+
+impl fmt::Display for Denial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::GuildOnly => write!(f, "This command can only be used on servers"),
+            Self::OwnerOnly => write!(f, "This command can only be used by the owner of the bot"),
+            Self::Disabled => write!(f, "This command is disabled on this server"),
+            Self::NsfwOnly => write!(f, "This command can only be used in NSFW channels"),
+            Self::UserPermissions(p) => write!(f, "You need the following permission(s) to use this command: {}", permission_names(p)),
+            Self::BotPermissions(p) => write!(f, "I need the following permission(s) to execute this command: {}", permission_names(p)),
+        }
+    }
+}
+
+/// What the requirements need to know about the channel of the message. Only asked if the command requires it
+pub(crate) trait ChannelLookup {
+    fn is_nsfw(&self) -> bool;
+
+    fn user_permissions(&self) -> Permissions;
+
+    fn bot_permissions(&self) -> Permissions;
+}
+
+/// Looks the channel and the permissions up in the cache
+struct CacheLookup<'a> {
+    ctx: &'a Context,
+    msg: &'a Message,
+}
+
+impl CacheLookup<'_> {
+    fn permissions(&self, user_id: UserId) -> Permissions {
+        match self.msg.guild(&self.ctx.cache) {
+            Some(g) => g.read().user_permissions_in(self.msg.channel_id, user_id),
+            None => Permissions::empty()
+        }
+    }
+}
+
+impl ChannelLookup for CacheLookup<'_> {
+    fn is_nsfw(&self) -> bool {
+        match self.msg.channel_id.to_channel_cached(&self.ctx.cache) {
+            Some(Channel::Guild(c)) => c.read().is_nsfw(),
+            _ => false
+        }
+    }
+
+    fn user_permissions(&self) -> Permissions {
+        self.permissions(self.msg.author.id)
+    }
+
+    fn bot_permissions(&self) -> Permissions {
+        let bot_id = self.ctx.cache.read().user.id;
+        self.permissions(bot_id)
+    }
+}
+
+/// Checks all requirements of the command. The guild settings are None in direct messages
+pub fn check(cmd: &Command, ctx: &Context, msg: &Message, owners: &[UserId], guild_settings: Option<&GuildSettings>) -> Result<(), Denial> {
+    check_with(cmd, msg.author.id, owners, guild_settings, &CacheLookup { ctx, msg })
+}
+
+pub(crate) fn check_with(cmd: &Command, author: UserId, owners: &[UserId], guild_settings: Option<&GuildSettings>, channel: &dyn ChannelLookup) -> Result<(), Denial> {
+    let req = &cmd.requirements;
+
+    if req.owner_only && !owners.contains(&author) {
+        return Err(Denial::OwnerOnly);
+    }
+
+    let guild_settings = match guild_settings {
+        Some(s) => s,
+        None => {
+            if req.guild_only || !req.user_permissions.is_empty() {
+                return Err(Denial::GuildOnly);
+            }
+            // DIRECT MESSAGES ARE NEVER NSFW CHANNELS
+            if req.nsfw_only {
+                return Err(Denial::NsfwOnly);
+            }
+            return Ok(());
+        }
+    };
+
+    if guild_settings.is_disabled(cmd) {
+        return Err(Denial::Disabled);
+    }
+    if req.nsfw_only && !channel.is_nsfw() {
+        return Err(Denial::NsfwOnly);
+    }
+
+    if !req.user_permissions.is_empty() {
+        let missing = req.user_permissions.difference(channel.user_permissions());
+        if !missing.is_empty() {
+            return Err(Denial::UserPermissions(missing));
+        }
+    }
+    if !req.bot_permissions.is_empty() {
+        let missing = req.bot_permissions.difference(channel.bot_permissions());
+        if !missing.is_empty() {
+            return Err(Denial::BotPermissions(missing));
+        }
+    }
+    Ok(())
+}
+
+const PERMISSION_NAMES: &[(Permissions, &str)] = &[
+    (Permissions::CREATE_INVITE, "Create Invite"),
+    (Permissions::KICK_MEMBERS, "Kick Members"),
+    (Permissions::BAN_MEMBERS, "Ban Members"),
+    (Permissions::ADMINISTRATOR, "Administrator"),
+    (Permissions::MANAGE_CHANNELS, "Manage Channels"),
+    (Permissions::MANAGE_GUILD, "Manage Server"),
+    (Permissions::ADD_REACTIONS, "Add Reactions"),
+    (Permissions::VIEW_AUDIT_LOG, "View Audit Log"),
+    (Permissions::PRIORITY_SPEAKER, "Priority Speaker"),
+    (Permissions::READ_MESSAGES, "Read Messages"),
+    (Permissions::SEND_MESSAGES, "Send Messages"),
+    (Permissions::SEND_TTS_MESSAGES, "Send TTS Messages"),
+    (Permissions::MANAGE_MESSAGES, "Manage Messages"),
+    (Permissions::EMBED_LINKS, "Embed Links"),
+    (Permissions::ATTACH_FILES, "Attach Files"),
+    (Permissions::READ_MESSAGE_HISTORY, "Read Message History"),
+    (Permissions::MENTION_EVERYONE, "Mention Everyone"),
+    (Permissions::USE_EXTERNAL_EMOJIS, "Use External Emojis"),
+    (Permissions::CONNECT, "Connect"),
+    (Permissions::SPEAK, "Speak"),
+    (Permissions::MUTE_MEMBERS, "Mute Members"),
+    (Permissions::DEAFEN_MEMBERS, "Deafen Members"),
+    (Permissions::MOVE_MEMBERS, "Move Members"),
+    (Permissions::USE_VAD, "Use Voice Activity"),
+    (Permissions::CHANGE_NICKNAME, "Change Nickname"),
+    (Permissions::MANAGE_NICKNAMES, "Manage Nicknames"),
+    (Permissions::MANAGE_ROLES, "Manage Roles"),
+    (Permissions::MANAGE_WEBHOOKS, "Manage Webhooks"),
+    (Permissions::MANAGE_EMOJIS, "Manage Emojis"),
+];
+
+/// Turns the permission flags into the names Discord shows (e.g. MANAGE_GUILD | EMBED_LINKS => "Manage Server, Embed Links")
+fn permission_names(p: Permissions) -> String {
+    PERMISSION_NAMES.iter()
+        .filter(|(flag, _)| p.contains(*flag))
+        .map(|(_, name)| *name)
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::command_framework::DEFAULT_TIMEOUT;
+
+    use super::*;
+
+    const OWNER: UserId = UserId(1);
+    const MEMBER: UserId = UserId(2);
+
+    struct MockChannel {
+        nsfw: bool,
+        user: Permissions,
+        bot: Permissions,
+    }
+
+    impl ChannelLookup for MockChannel {
+        fn is_nsfw(&self) -> bool {
+            self.nsfw
+        }
+
+        fn user_permissions(&self) -> Permissions {
+            self.user
+        }
+
+        fn bot_permissions(&self) -> Permissions {
+            self.bot
+        }
+    }
+
+    const SFW: MockChannel = MockChannel { nsfw: false, user: Permissions::SEND_MESSAGES, bot: Permissions::SEND_MESSAGES };
+
+    fn command(requirements: &'static Requirements) -> Command {
+        Command {
+            key: "test",
+            aliases: &[],
+            description: "",
+            params: &[],
+            category: Category::Misc,
+            cooldowns: &[],
+            requirements,
+            timeout: DEFAULT_TIMEOUT,
+            func: |_| Ok(MarkAsSucceeded),
+        }
+    }
+
+    fn settings() -> GuildSettings {
+        serde_json::from_str(r#"{"_id": 3}"#).expect("could not parse mock settings")
+    }
+
+    fn check(req: &'static Requirements, author: UserId, settings: Option<&GuildSettings>, channel: &MockChannel) -> Result<(), Denial> {
+        check_with(&command(req), author, &[OWNER], settings, channel)
+    }
+
+    #[test]
+    fn no_requirements() {
+        assert!(check(&Requirements::NONE, MEMBER, None, &SFW).is_ok());
+        assert!(check(&Requirements::NONE, MEMBER, Some(&settings()), &SFW).is_ok());
+    }
+
+    #[test]
+    fn owner_only() {
+        const REQ: Requirements = Requirements { owner_only: true, ..Requirements::NONE };
+        match check(&REQ, MEMBER, Some(&settings()), &SFW) {
+            Err(Denial::OwnerOnly) => {}
+            other => panic!("expected owner only, got {:?}", other)
+        }
+        assert!(check(&REQ, OWNER, Some(&settings()), &SFW).is_ok());
+        assert!(check(&REQ, OWNER, None, &SFW).is_ok());
+    }
+
+    #[test]
+    fn guild_only() {
+        const REQ: Requirements = Requirements { guild_only: true, ..Requirements::NONE };
+        match check(&REQ, MEMBER, None, &SFW) {
+            Err(Denial::GuildOnly) => {}
+            other => panic!("expected guild only, got {:?}", other)
+        }
+        assert!(check(&REQ, MEMBER, Some(&settings()), &SFW).is_ok());
+
+        // USER PERMISSIONS CANNOT BE CHECKED IN DIRECT MESSAGES
+        const PERMS: Requirements = Requirements { user_permissions: Permissions::MANAGE_GUILD, ..Requirements::NONE };
+        match check(&PERMS, MEMBER, None, &SFW) {
+            Err(Denial::GuildOnly) => {}
+            other => panic!("expected guild only, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn nsfw_only() {
+        const REQ: Requirements = Requirements { nsfw_only: true, ..Requirements::NONE };
+        match check(&REQ, MEMBER, None, &SFW) {
+            Err(Denial::NsfwOnly) => {}
+            other => panic!("expected nsfw only in direct messages, got {:?}", other)
+        }
+        match check(&REQ, MEMBER, Some(&settings()), &SFW) {
+            Err(Denial::NsfwOnly) => {}
+            other => panic!("expected nsfw only, got {:?}", other)
+        }
+        let nsfw = MockChannel { nsfw: true, ..SFW };
+        assert!(check(&REQ, MEMBER, Some(&settings()), &nsfw).is_ok());
+    }
+
+    #[test]
+    fn disabled() {
+        let mut settings = settings();
+        settings.disabled_commands.push("test".to_owned());
+        match check(&Requirements::NONE, MEMBER, Some(&settings), &SFW) {
+            Err(Denial::Disabled) => {}
+            other => panic!("expected disabled, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn user_permissions() {
+        const REQ: Requirements = Requirements { user_permissions: Permissions::MANAGE_GUILD, ..Requirements::NONE };
+        match check(&REQ, MEMBER, Some(&settings()), &SFW) {
+            Err(Denial::UserPermissions(p)) => assert_eq!(p, Permissions::MANAGE_GUILD),
+            other => panic!("expected missing user permissions, got {:?}", other)
+        }
+        let admin = MockChannel { user: Permissions::MANAGE_GUILD | Permissions::SEND_MESSAGES, ..SFW };
+        assert!(check(&REQ, MEMBER, Some(&settings()), &admin).is_ok());
+    }
+
+    #[test]
+    fn bot_permissions() {
+        const REQ: Requirements = Requirements::bot_permissions(Permissions::from_bits_truncate(Permissions::ATTACH_FILES.bits() | Permissions::EMBED_LINKS.bits()));
+        let channel = MockChannel { bot: Permissions::EMBED_LINKS, ..SFW };
+        match check(&REQ, MEMBER, Some(&settings()), &channel) {
+            Err(Denial::BotPermissions(p)) => assert_eq!(p, Permissions::ATTACH_FILES),
+            other => panic!("expected missing bot permissions, got {:?}", other)
+        }
+        let channel = MockChannel { bot: Permissions::EMBED_LINKS | Permissions::ATTACH_FILES, ..SFW };
+        assert!(check(&REQ, MEMBER, Some(&settings()), &channel).is_ok());
+        // THE BOT DOES NOT NEED PERMISSIONS IN DIRECT MESSAGES
+        assert!(check(&REQ, MEMBER, None, &SFW).is_ok());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(permission_names(Permissions::MANAGE_GUILD | Permissions::EMBED_LINKS), "Manage Server, Embed Links");
+        assert_eq!(permission_names(Permissions::USE_VAD), "Use Voice Activity");
+        assert_eq!(permission_names(Permissions::empty()), "");
+        assert_eq!(Denial::BotPermissions(Permissions::ATTACH_FILES).to_string(), "I need the following permission(s) to execute this command: Attach Files");
+    }
+
+    #[test]
+    fn every_permission_has_a_name() {
+        let named = PERMISSION_NAMES.iter().fold(Permissions::empty(), |all, (flag, _)| all | *flag);
+        assert_eq!(named, Permissions::all());
+    }
+}
\ No newline at end of file
@@ -4,9 +4,13 @@ use crate::command_framework::prelude::*;
 
 pub static ABOUT_COMMAND: Command = Command {
     key: "about",
+    aliases: &["info"],
     description: "Shows some info about this bot",
-    help_page: "",
+    params: &[],
     category: Category::Misc,
+    cooldowns: &[Cooldown::new(CooldownBucket::Channel, 2, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: about_command,
 };
 
@@ -29,7 +33,7 @@ fn about_command(args: CommandArguments) -> CommandResult {
                 let cache = args.ctx.cache.read();
                 avatar_url = cache.user.avatar_url();
                 username = cache.user.name.clone();
-                member = cache.member(&args.m.guild_id.unwrap(), cache.user.id);
+                member = args.guild_id().and_then(|g| cache.member(g, cache.user.id));
                 shard_count = cache.shard_count;
             } // DROP CACHE LOCK
 
@@ -60,7 +64,7 @@ fn about_command(args: CommandArguments) -> CommandResult {
                 ✅ Memes\n\
                 ✅ Nsfw ;)\n\
                 ✅ Administration Commands\n\
-                ```", username, args.settings.default_prefix));
+                ```", username, args.prefix));
 
 
             let statistics;
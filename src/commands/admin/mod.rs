@@ -0,0 +1,2 @@
+pub mod prefix;
+pub mod toggle;
\ No newline at end of file
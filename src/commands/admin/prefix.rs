@@ -0,0 +1,47 @@
+use crate::command_framework::prelude::*;
+
+pub static PREFIX_COMMAND: Command = Command {
+    key: "prefix",
+    aliases: &[],
+    description: "Shows or changes the prefix of this server",
+    params: &[ArgumentSpec::optional("new prefix", ArgumentKind::Rest)],
+    category: Category::Admin,
+    cooldowns: &[Cooldown::new(CooldownBucket::Guild, 3, 30)],
+    requirements: &Requirements {
+        user_permissions: Permissions::MANAGE_GUILD,
+        guild_only: true,
+        ..Requirements::NONE
+    },
+    timeout: DEFAULT_TIMEOUT,
+    func: prefix_command,
+};
+
+const MAX_PREFIX_LENGTH: usize = 10;
+
+fn prefix_command(args: CommandArguments) -> CommandResult {
+    let guild_id = args.require_guild()?.id;
+
+    let new_prefix = match args.params.text("new prefix") {
+        Some(s) => s,
+        None => {
+            let _ = args.m.reply(args.ctx, format!("The prefix of this server is ``{}``", args.prefix));
+            return Ok(MarkAsSucceeded);
+        }
+    };
+    if new_prefix.contains(char::is_whitespace) {
+        return Ok(PrintUsage);
+    }
+
+    if new_prefix.chars().count() > MAX_PREFIX_LENGTH {
+        let _ = args.m.reply(args.ctx, format!("The prefix must not be longer than {} characters", MAX_PREFIX_LENGTH));
+        return Ok(MarkAsFailed);
+    }
+
+    let default_prefix = args.settings.default_prefix.clone();
+    unwrap_cmd_err!(Internal, args.guild_settings.update(guild_id, |s| {
+        s.prefix = if new_prefix == default_prefix { None } else { Some(new_prefix.to_owned()) };
+    }), "I could not save the prefix");
+
+    let _ = args.m.reply(args.ctx, format!("The prefix of this server is now ``{}``", new_prefix));
+    Ok(MarkAsSucceeded)
+}
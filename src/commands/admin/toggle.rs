@@ -0,0 +1,113 @@
+use crate::command_framework::prelude::*;
+
+const TOGGLE_REQUIREMENTS: Requirements = Requirements {
+    user_permissions: Permissions::MANAGE_GUILD,
+    guild_only: true,
+    ..Requirements::NONE
+};
+
+pub static DISABLE_COMMAND: Command = Command {
+    key: "disable",
+    aliases: &[],
+    description: "Disables a command or a whole category on this server. Lists everything disabled if no name is given",
+    params: &[ArgumentSpec::optional("command or category", ArgumentKind::Rest)],
+    category: Category::Admin,
+    cooldowns: &[Cooldown::new(CooldownBucket::Guild, 5, 30)],
+    requirements: &TOGGLE_REQUIREMENTS,
+    timeout: DEFAULT_TIMEOUT,
+    func: disable_command,
+};
+
+pub static ENABLE_COMMAND: Command = Command {
+    key: "enable",
+    aliases: &[],
+    description: "Enables a disabled command or category on this server again",
+    params: &[ArgumentSpec::new("command or category", ArgumentKind::Rest)],
+    category: Category::Admin,
+    cooldowns: &[Cooldown::new(CooldownBucket::Guild, 5, 30)],
+    requirements: &TOGGLE_REQUIREMENTS,
+    timeout: DEFAULT_TIMEOUT,
+    func: enable_command,
+};
+
+enum Target {
+    Command(&'static str),
+    Category(Category),
+}
+
+fn disable_command(args: CommandArguments) -> CommandResult {
+    let name = match args.params.text("command or category") {
+        Some(s) => s,
+        None => return list_disabled(&args)
+    };
+    toggle(&args, name, true)
+}
+
+fn enable_command(args: CommandArguments) -> CommandResult {
+    let name = args.params.text("command or category").unwrap();
+    toggle(&args, name, false)
+}
+
+fn toggle(args: &CommandArguments, name: &str, disable: bool) -> CommandResult {
+    let guild_id = args.require_guild()?.id;
+
+    let target = match find_target(args, name) {
+        Some(t) => t,
+        None => {
+            let _ = args.m.reply(args.ctx, format!("There is no command or category called ``{}``", name));
+            return Ok(MarkAsFailed);
+        }
+    };
+    let is_admin = match target {
+        Target::Command(key) => args.handler.read().get_command(key).map(|c| c.category == Category::Admin).unwrap_or(false),
+        Target::Category(ref c) => *c == Category::Admin,
+    };
+    if is_admin {
+        let _ = args.m.reply(args.ctx, "Admin commands cannot be disabled");
+        return Ok(MarkAsFailed);
+    }
+
+    unwrap_cmd_err!(Internal, args.guild_settings.update(guild_id, |s| {
+        let (list, value) = match target {
+            Target::Command(key) => (&mut s.disabled_commands, key.to_owned()),
+            Target::Category(ref c) => (&mut s.disabled_categories, c.to_string()),
+        };
+        list.retain(|v| *v != value);
+        if disable {
+            list.push(value);
+        }
+    }), "I could not save the settings of this server");
+
+    let what = match target {
+        Target::Command(key) => format!("The command ``{}{}``", args.prefix, key),
+        Target::Category(ref c) => format!("The category ``{}``", c),
+    };
+    let _ = args.m.reply(args.ctx, format!("{} is now {} on this server", what, if disable { "disabled" } else { "enabled" }));
+    Ok(MarkAsSucceeded)
+}
+
+/// Commands take precedence over categories
+fn find_target(args: &CommandArguments, name: &str) -> Option<Target> {
+    if let Some(cmd) = args.handler.read().get_command(name) {
+        return Some(Target::Command(cmd.key));
+    }
+    Category::from_name(name).map(Target::Category)
+}
+
+fn list_disabled(args: &CommandArguments) -> CommandResult {
+    let settings = &args.require_guild()?.settings;
+    if settings.disabled_commands.is_empty() && settings.disabled_categories.is_empty() {
+        let _ = args.m.reply(args.ctx, "Nothing is disabled on this server");
+        return Ok(MarkAsSucceeded);
+    }
+
+    let mut s = String::from("Disabled on this server:");
+    if !settings.disabled_categories.is_empty() {
+        s.push_str(&format!("\nCategories: ``{}``", settings.disabled_categories.join("``, ``")));
+    }
+    if !settings.disabled_commands.is_empty() {
+        s.push_str(&format!("\nCommands: ``{}``", settings.disabled_commands.join("``, ``")));
+    }
+    let _ = args.m.reply(args.ctx, s);
+    Ok(MarkAsSucceeded)
+}
\ No newline at end of file
@@ -6,9 +6,13 @@ use crate::commands;
 
 pub static AWW_COMMAND: Command = Command {
     key: "aww",
+    aliases: &[],
     description: "r/aww",
-    help_page: "",
+    params: &[],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 5, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: aww_command,
 };
 
@@ -20,8 +24,7 @@ fn aww_command(args: CommandArguments) -> CommandResult {
         let awws = match safe.get::<Vec<Aww>>() {
             Some(s) => s,
             None => {
-                let _ = args.m.reply(args.ctx, "Sorry! No awws fetched yet :c Please try again later :dog2:");
-                return Ok(MarkAsFailed);
+                return Err(CommandError::missing_cache("Sorry! No awws fetched yet :c Please try again later :dog2:"));
             }
         };
         aww = awws[rand::thread_rng().gen_range(0, awws.len())].clone();
@@ -6,9 +6,13 @@ use crate::commands;
 
 pub static BIRB_COMMAND: Command = Command {
     key: "birb",
+    aliases: &["bird"],
     description: "Gets a random birb",
-    help_page: "",
+    params: &[],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 5, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: birb_command,
 };
 
@@ -27,8 +31,7 @@ fn birb_command(args: CommandArguments) -> CommandResult {
         let birb_cache = match safe.get::<Vec<String>>() {
             Some(ks) => ks,
             None => {
-                let _ = args.m.reply(args.ctx, "Sorry, no birbs cached yet! Please try again later :)! :bird:");
-                return Ok(MarkAsFailed);
+                return Err(CommandError::missing_cache("Sorry, no birbs cached yet! Please try again later :)! :bird:"));
             }
         };
 
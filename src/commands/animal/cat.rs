@@ -5,22 +5,26 @@ use crate::commands;
 
 pub static CAT_COMMAND: Command = Command {
     key: "cat",
+    aliases: &["kitty"],
     description: "Gives you a random cat",
-    help_page: "",
+    params: &[],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 3, 15), Cooldown::new(CooldownBucket::Global, 30, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: cat_command,
 };
 
 fn cat_command(args: CommandArguments) -> CommandResult {
-    let mut res = unwrap_cmd_err!(&CAT_COMMAND, reqwest::get("https://aws.random.cat/meow"), "could not connect to cat service");
-    let text = unwrap_cmd_err!(&CAT_COMMAND, res.text(), "could not read cat service's body");
+    let mut res = unwrap_cmd_err!(UpstreamUnavailable, reqwest::get("https://aws.random.cat/meow"), "the cat service is not reachable right now");
+    let text = unwrap_cmd_err!(UpstreamUnavailable, res.text(), "the cat service did not answer properly");
 
     #[derive(Serialize, Deserialize)]
     struct WebResponse {
         file: String
     }
 
-    let cat_url: WebResponse = unwrap_cmd_err!(&CAT_COMMAND, serde_json::from_str(&text), "could not parse cat service's json body");
+    let cat_url: WebResponse = unwrap_cmd_err!(UpstreamUnavailable, serde_json::from_str(&text), "the cat service did not answer properly");
 
     // POST IT
     let _ = args.m.channel_id.send_message(args.ctx, |mb| mb.embed(|mut eb| {
@@ -6,17 +6,25 @@ use crate::commands;
 
 pub static DOG_COMMAND: Command = Command {
     key: "dog",
+    aliases: &["doggo"],
     description: "Shows you a dog :)!",
-    help_page: "[<optional: breed>]",
+    params: &[ArgumentSpec::optional("breed", ArgumentKind::Quoted)],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 5, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: dog_command,
 };
 
 pub static DOG_BREEDS_COMMAND: Command = Command {
     key: "dogbreeds",
+    aliases: &["breeds"],
     description: "Shows you all available breeds",
-    help_page: "",
+    params: &[],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 5, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
 
     func: dog_breed_command,
 };
@@ -31,8 +39,6 @@ pub struct DogBreed {
 }
 
 fn dog_command(args: CommandArguments) -> CommandResult {
-    let split: Vec<&str> = args.m.content.split_whitespace().collect();
-
     #[derive(Serialize, Deserialize)]
     struct DogResponse {
         message: String,
@@ -46,26 +52,27 @@ fn dog_command(args: CommandArguments) -> CommandResult {
         let dog_cache = match safe.get::<DogCache>() {
             Some(s) => s,
             None => {
-                let _ = args.m.reply(args.ctx, "Sorry, no dogs cached yet! Please try again later :dog2:");
-                return Ok(MarkAsFailed);
+                return Err(CommandError::missing_cache("Sorry, no dogs cached yet! Please try again later :dog2:"));
             }
         };
 
         let dog_breed;
 
-        if split.len() == 1 {
-            // RANDOM DOG
-            let index = rand::thread_rng().gen_range(0, dog_cache.breeds.len());
-            dog_breed = dog_cache.breeds.get(index).unwrap();
-        } else if split.len() == 2 {
-            dog_breed = match dog_cache.breeds.iter().find(|b| b.name.to_lowercase() == split[1].to_lowercase()) {
+        if let Some(breed) = args.params.text("breed") {
+            dog_breed = match dog_cache.breeds.iter().find(|b| b.name.to_lowercase() == breed.to_lowercase()) {
                 Some(s) => s,
                 None => {
-                    let _ = args.m.reply(args.ctx, format!("Dog breed not found! View all breeds using ``{}dogbreeds``", args.settings.default_prefix));
-                    return Ok(MarkAsFailed);
+                    return Err(CommandError::BadInput {
+                        message: format!("Dog breed not found! View all breeds using ``{}dogbreeds``", args.prefix),
+                        source: None,
+                    });
                 }
             };
-        } else { return Ok(PrintUsage); }
+        } else {
+            // RANDOM DOG
+            let index = rand::thread_rng().gen_range(0, dog_cache.breeds.len());
+            dog_breed = dog_cache.breeds.get(index).unwrap();
+        }
 
         let index = rand::thread_rng().gen_range(0, dog_breed.images.len());
         dog_url = dog_breed.images.get(index).unwrap().clone();
@@ -95,8 +102,7 @@ fn dog_breed_command(args: CommandArguments) -> CommandResult {
         let dog_cache = match safe.get::<DogCache>() {
             Some(s) => s,
             None => {
-                let _ = args.m.reply(args.ctx, "Sorry, no dog breeds cached yet! Please try again later");
-                return Ok(MarkAsFailed);
+                return Err(CommandError::missing_cache("Sorry, no dog breeds cached yet! Please try again later"));
             }
         };
 
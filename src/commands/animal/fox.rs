@@ -5,16 +5,20 @@ use crate::commands;
 
 pub static FOX_COMMAND: Command = Command {
     key: "fox",
+    aliases: &[],
     description: "Gives you random fox",
-    help_page: "",
+    params: &[],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 3, 15), Cooldown::new(CooldownBucket::Global, 30, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: fox_command,
 };
 
 fn fox_command(args: CommandArguments) -> CommandResult {
-    let mut res = unwrap_cmd_err!(&FOX_COMMAND, reqwest::get("https://randomfox.ca/floof/"), "could not fetch random fox from service");
-    let text = unwrap_cmd_err!(&FOX_COMMAND, res.text(), "could not read body from fox service's response");
-    let fox: FoxResponse = unwrap_cmd_err!(&FOX_COMMAND, serde_json::from_str(&text), "could not parse json body from fox's service");
+    let mut res = unwrap_cmd_err!(UpstreamUnavailable, reqwest::get("https://randomfox.ca/floof/"), "the fox service is not reachable right now");
+    let text = unwrap_cmd_err!(UpstreamUnavailable, res.text(), "the fox service did not answer properly");
+    let fox: FoxResponse = unwrap_cmd_err!(UpstreamUnavailable, serde_json::from_str(&text), "the fox service did not answer properly");
 
     let _ = args.m.channel_id.send_message(args.ctx, |mb| {
         mb.embed(|mut eb| {
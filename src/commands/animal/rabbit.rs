@@ -6,9 +6,13 @@ use crate::commands;
 
 pub static RABBIT_COMMAND: Command = Command {
     key: "rabbit",
+    aliases: &["bunny"],
     description: "Gets you an hoppyboi",
-    help_page: "",
+    params: &[],
     category: Category::Animals,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 5, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: rabbit_command,
 };
 
@@ -20,8 +24,7 @@ fn rabbit_command(args: CommandArguments) -> CommandResult {
         let rabbits = match safe.get::<Vec<Rabbit>>() {
             Some(s) => s,
             None => {
-                let _ = args.m.reply(args.ctx, "Sorry, no rabbits fetched yet :c Try again later :rabbit2:");
-                return Ok(MarkAsFailed);
+                return Err(CommandError::missing_cache("Sorry, no rabbits fetched yet :c Try again later :rabbit2:"));
             }
         };
         rabbit = rabbits[rand::thread_rng().gen_range(0, rabbits.len())].clone();
@@ -9,9 +9,17 @@ pub enum Category {
     Fun,
     Misc,
     Animals,
+    Admin,
 }
 
 impl Category {
+    pub const ALL: [Category; 5] = [Category::GeneratedImage, Category::Fun, Category::Misc, Category::Animals, Category::Admin];
+
+    /// Finds a category by its display name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Category> {
+        Self::ALL.iter().find(|c| c.to_string().eq_ignore_ascii_case(name)).cloned()
+    }
+
     /*pub fn show_on_help(&self) -> bool {
         match *self {
             Self::GeneratedImage => false,
@@ -27,6 +35,7 @@ impl Category {
             Self::Fun => "😄",
             Self::Misc => "❔",
             Self::Animals => "😺",
+            Self::Admin => "🛠",
         }
     }
 }
@@ -38,6 +47,7 @@ impl Display for Category {
             Self::Fun => write!(f, "Fun"),
             Self::Misc => write!(f, "Misc"),
             Self::Animals => write!(f, "Animals"),
+            Self::Admin => write!(f, "Admin"),
         }
     }
 }
\ No newline at end of file
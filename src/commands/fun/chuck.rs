@@ -5,9 +5,13 @@ use crate::commands;
 
 pub static CHUCK_COMMAND: Command = Command {
     key: "chuck",
+    aliases: &["norris"],
     description: "Gets you a random chuck norris joke",
-    help_page: "",
+    params: &[],
     category: Category::Fun,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 3, 15), Cooldown::new(CooldownBucket::Global, 30, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: chuck_command,
 };
 
@@ -15,10 +19,10 @@ fn chuck_command(args: CommandArguments) -> CommandResult {
     let first = "Chuck";
     let second = "Norris";
 
-    let mut res = unwrap_cmd_err!(&CHUCK_COMMAND, reqwest::get(&format!("http://api.icndb.com/jokes/random")), "could not fetch chuck norris joke from service");
-    let text = unwrap_cmd_err!(&CHUCK_COMMAND, res.text(), "could not read chuck norris joke's body");
+    let mut res = unwrap_cmd_err!(UpstreamUnavailable, reqwest::get("http://api.icndb.com/jokes/random"), "the Chuck Norris joke service is not reachable right now");
+    let text = unwrap_cmd_err!(UpstreamUnavailable, res.text(), "the Chuck Norris joke service did not answer properly");
 
-    let joke: ChuckNorrisResponse = unwrap_cmd_err!(&CHUCK_COMMAND, serde_json::from_str(&text), "could not parse json from chuck norris joke");
+    let joke: ChuckNorrisResponse = unwrap_cmd_err!(UpstreamUnavailable, serde_json::from_str(&text), "the Chuck Norris joke service did not answer properly");
     let joke = joke.value.joke.replace("&quot;", "**");
 
     let _ = args.m.channel_id.send_message(args.ctx, |mb| {
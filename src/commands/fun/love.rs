@@ -1,41 +1,26 @@
 use rand::{Rng, SeedableRng};
-use serenity::model::guild::Member;
+use serenity::model::user::User;
 
 use crate::command_framework::prelude::*;
 
 pub static LOVE_COMMAND: Command = Command {
     key: "love",
+    aliases: &["ship"],
     description: "Its a match! maybe.",
-    help_page: "[<optional: lover>] <loved: @User>",
+    params: &[ArgumentSpec::optional("lover", ArgumentKind::User), ArgumentSpec::new("loved", ArgumentKind::User)],
     category: Category::Fun,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 3, 10)],
+    requirements: &Requirements::NONE,
+    timeout: DEFAULT_TIMEOUT,
     func: love_command,
 };
 
 fn love_command(args: CommandArguments) -> CommandResult {
-    let cache;
-    {
-        cache = args.ctx.cache.read().clone();
-    } // Drop Lock
+    let user1: &User = args.params.user("lover").unwrap_or(&args.m.author);
+    let user2: &User = args.params.user("loved").unwrap();
 
-    let user1: Member;
-    let user2: Member;
-
-    match args.m.mentions.len() {
-        0 => {
-            return Ok(PrintUsage);
-        }
-        1 => {
-            user1 = cache.member(args.m.guild_id.unwrap(), args.m.author.id).unwrap();
-            user2 = cache.member(args.m.guild_id.unwrap(), args.m.mentions.get(0).unwrap()).unwrap();
-        }
-        _ => {
-            user1 = cache.member(args.m.guild_id.unwrap(), args.m.mentions.get(0).unwrap()).unwrap();
-            user2 = cache.member(args.m.guild_id.unwrap(), args.m.mentions.get(1).unwrap()).unwrap();
-        }
-    }
-
-    let mut rnd = rand::rngs::StdRng::seed_from_u64(user1.user.read().id.0 + user2.user.read().id.0).gen_range(0, 101);
-    if user1.user.read().id.0 == user2.user.read().id.0 {
+    let mut rnd = rand::rngs::StdRng::seed_from_u64(user1.id.0 + user2.id.0).gen_range(0, 101);
+    if user1.id.0 == user2.id.0 {
         rnd = 101;
     }
     let _ = args.m.channel_id.send_message(args.ctx, |f| {
@@ -6,9 +6,13 @@ use crate::commands;
 
 pub static MEME_COMMAND: Command = Command {
     key: "meme",
+    aliases: &["memes"],
     description: "Gets a random meme",
-    help_page: "",
+    params: &[],
     category: Category::Fun,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 5, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS),
+    timeout: DEFAULT_TIMEOUT,
     func: meme_command,
 };
 
@@ -26,8 +30,7 @@ fn meme_command(args: CommandArguments) -> CommandResult {
     let meme_cache = safe.get::<Vec<Meme>>();
 
     if meme_cache.is_none() {
-        let _ = args.m.channel_id.say(args.ctx, "No memes fetched yet! Try again later");
-        return Ok(MarkAsFailed);
+        return Err(CommandError::missing_cache("No memes fetched yet! Try again later"));
     }
     let meme_cache = meme_cache.unwrap();
 
@@ -1,26 +1,23 @@
-use crate::command_framework::{Command, CommandArguments, CommandResult};
-use crate::command_framework::CommandAction::{MarkAsSucceeded, PrintUsage};
+use crate::command_framework::{ArgumentKind, ArgumentSpec, Command, CommandArguments, CommandResult, Cooldown, CooldownBucket, DEFAULT_TIMEOUT, Requirements};
+use crate::command_framework::CommandAction::MarkAsSucceeded;
 use crate::commands::category::Category;
 
 pub static SAY_COMMAND: Command = Command {
     key: "say",
+    aliases: &["echo"],
     description: "I say what you want me to say",
-    help_page: "<Message>",
+    params: &[ArgumentSpec::new("message", ArgumentKind::Rest)],
     category: Category::Fun,
+    cooldowns: &[Cooldown::new(CooldownBucket::Channel, 3, 10)],
+    requirements: &Requirements::NONE,
+    timeout: DEFAULT_TIMEOUT,
     func: say_command,
 };
 
 fn say_command(args: CommandArguments) -> CommandResult {
-    let v: Vec<&str> = args.m.content.splitn(2, ' ').collect();
-    return match v.get(1) {
-        Some(t) => {
-            let _ = args.m.channel_id.send_message(args.ctx, |f| {
-                f.content(t)
-            });
-            Ok(MarkAsSucceeded)
-        }
-        None => {
-            Ok(PrintUsage)
-        }
-    };
+    let t = args.params.text("message").unwrap();
+    let _ = args.m.channel_id.send_message(args.ctx, |f| {
+        f.content(t)
+    });
+    Ok(MarkAsSucceeded)
 }
\ No newline at end of file
@@ -5,9 +5,13 @@ use crate::command_framework::prelude::*;
 
 pub static URBAN_COMMAND: Command = Command {
     key: "urban",
+    aliases: &["ud"],
     description: "Searches a term on UrbanDictionary",
-    help_page: "<term>",
+    params: &[ArgumentSpec::new("term", ArgumentKind::Rest)],
     category: Category::Fun,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 3, 15), Cooldown::new(CooldownBucket::Global, 30, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS.union(Permissions::ATTACH_FILES)),
+    timeout: DEFAULT_TIMEOUT,
     func: urban_command,
 };
 
@@ -26,17 +30,11 @@ struct UrbanResponse {
 }
 
 fn urban_command(args: CommandArguments) -> CommandResult {
-    let split: Vec<&str> = args.m.content.split_whitespace().collect();
-    if split.len() < 2 {
-        return Ok(PrintUsage);
-    }
-    let term = split.into_iter().skip(1);
-    let term: Vec<&str> = term.collect();
-    let term = term.join(" ");
-    let mut res = unwrap_cmd_err!(&URBAN_COMMAND, reqwest::get( reqwest::Url::parse(&format!("https://api.urbandictionary.com/v0/define?term={}", term)).unwrap()), "could not make request to urban dictionary");
+    let term = args.params.text("term").unwrap();
+    let mut res = unwrap_cmd_err!(UpstreamUnavailable, reqwest::get( reqwest::Url::parse(&format!("https://api.urbandictionary.com/v0/define?term={}", term)).unwrap()), "UrbanDictionary is not reachable right now");
 
-    let text: String = unwrap_cmd_err!(&URBAN_COMMAND, res.text(), "could not read urban dictionary's body");
-    let mut uo: FullUrbanResponse = unwrap_cmd_err!(&URBAN_COMMAND, serde_json::from_str(&text), "could not parse urban dictionary's json body");
+    let text: String = unwrap_cmd_err!(UpstreamUnavailable, res.text(), "UrbanDictionary did not answer properly");
+    let mut uo: FullUrbanResponse = unwrap_cmd_err!(UpstreamUnavailable, serde_json::from_str(&text), "UrbanDictionary did not answer properly");
 
     if uo.list.len() == 0 {
         let _ = args.m.reply(args.ctx, "Term not found. I'm sorry :c");
@@ -50,7 +48,7 @@ fn urban_command(args: CommandArguments) -> CommandResult {
     uo.example = uo.example.replace("]", "");
 
 
-    let mug = super::urbanmug::get_mug(&term).unwrap_or_default();
+    let mug = super::urbanmug::get_mug(term).unwrap_or_default();
 
     let mock_mug_filename = format!("{}.jpg", uo.defid);
 
@@ -58,7 +56,7 @@ fn urban_command(args: CommandArguments) -> CommandResult {
         mb.embed(|mut eb| {
             eb.thumbnail(format!("attachment://{}", &mock_mug_filename));
             eb.color(Colour::from_rgb(239, 255, 0));
-            eb.title(format!(r#"Urban Dictionary: "{}""#, &term));
+            eb.title(format!(r#"Urban Dictionary: "{}""#, term));
             eb.url(&uo.permalink);
             eb.description(format!("By user {}", &uo.author));
             eb.field("Definition", super::super::util::shorten_string(&uo.definition, super::super::util::DISCORD_EMBED_FIELD_VALE_MAX_LENGTH), false);
@@ -7,32 +7,24 @@ use crate::command_framework::prelude::*;
 
 pub static URBANMUG_COMMAND: Command = Command {
     key: "urbanmug",
+    aliases: &["mug"],
     description: "Gets a nice mug",
-    help_page: "<text>",
+    params: &[ArgumentSpec::new("text", ArgumentKind::Rest)],
     category: Category::Fun,
+    cooldowns: &[Cooldown::new(CooldownBucket::User, 3, 15), Cooldown::new(CooldownBucket::Global, 30, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS.union(Permissions::ATTACH_FILES)),
+    timeout: DEFAULT_TIMEOUT,
     func: mug_command,
 };
 
 fn mug_command(args: CommandArguments) -> CommandResult {
-    let split: Vec<&str> = args.m.content.split_whitespace().collect();
+    let s = args.params.text("text").unwrap();
 
-    if split.len() < 2 {
-        return Ok(PrintUsage);
-    }
-
-    let s = split[1..split.len()].join(" ");
-
-    let mug = match get_mug(&s) {
-        Ok(s) => s,
-        Err(_) => {
-            let _ = args.m.reply(args.ctx, "Sorry, it looks like UrbanDictionary has some problems... Please try again later!");
-            return Ok(MarkAsFailed);
-        }
-    };
+    let mug = unwrap_cmd_err!(UpstreamUnavailable, get_mug(s), "it looks like UrbanDictionary has some problems");
 
     let _ = args.m.channel_id.send_message(args.ctx, |mb| {
         mb.embed(|mut eb| {
-            eb.title(format!(r#"The "{}" mug"#, &s));
+            eb.title(format!(r#"The "{}" mug"#, s));
             eb.color(Colour::from_rgb(239, 255, 0));
             eb.image("attachment://urban_mug.jpg");
 
@@ -7,9 +7,13 @@ use crate::util::eventwaiter::{EventAction, ReactionEvent, ResponseAccess};
 
 pub static HELP_COMMAND: Command = Command {
     key: "help",
+    aliases: &["commands"],
     description: "This help page",
-    help_page: "",
+    params: &[],
     category: Category::Misc,
+    cooldowns: &[Cooldown::new(CooldownBucket::Channel, 2, 10)],
+    requirements: &Requirements::bot_permissions(Permissions::EMBED_LINKS.union(Permissions::ADD_REACTIONS)),
+    timeout: DEFAULT_TIMEOUT,
     func: help_command,
 };
 
@@ -17,13 +21,13 @@ fn help_command(args: CommandArguments) -> CommandResult {
     let handler = args.handler.read();
     let mut cmds = handler.get_all_commands().clone();
     cmds.sort_by(|a, b| a.category.to_string().cmp(&b.category.to_string()));
-    print_cmds(&args, cmds, "Help ");
+    print_cmds(&args, cmds, "Help ")?;
     Ok(MarkAsSucceeded)
 }
 
 //noinspection DuplicatedCode
-pub(super) fn print_cmds(args: &CommandArguments, cmds: Vec<Command>, title: &str) {
-    let message = args.m.channel_id.send_message(&args.ctx, |mb| {
+pub(super) fn print_cmds(args: &CommandArguments, cmds: Vec<Command>, title: &str) -> Result<(), CommandError> {
+    let msg = unwrap_cmd_err!(DiscordApi, args.m.channel_id.send_message(args.ctx, |mb| {
         mb.embed(|mut eb| {
             let mut s = String::new();
             for cmd in cmds.iter() {
@@ -31,10 +35,11 @@ pub(super) fn print_cmds(args: &CommandArguments, cmds: Vec<Command>, title: &st
                     continue;
                 }
                 eb.title(title.to_string() + &cmd.category.to_string());
-                if cmd.help_page == "" {
-                    s.push_str(&format!("``{}{}`` => {}\n", args.settings.default_prefix, cmd.key, cmd.description));
+                let usage = cmd.usage();
+                if usage.is_empty() {
+                    s.push_str(&format!("``{}{}`` => {}\n", args.prefix, cmd.key, cmd.description));
                 } else {
-                    s.push_str(&format!("``{}{} {}`` => {}\n", args.settings.default_prefix, cmd.key, cmd.help_page, cmd.description));
+                    s.push_str(&format!("``{}{} {}`` => {}\n", args.prefix, cmd.key, usage, cmd.description));
                 }
             }
             eb.description(s);
@@ -43,9 +48,8 @@ pub(super) fn print_cmds(args: &CommandArguments, cmds: Vec<Command>, title: &st
             super::util::add_timestamp(&mut eb);
             eb
         })
-    });
+    }), "I could not send the help message");
     let mut emotes: Vec<String> = vec![];
-    let msg = message.as_ref().unwrap();
 
     for cmd in cmds {
         let s = cmd.category.get_category_emoji().to_string();
@@ -55,7 +59,7 @@ pub(super) fn print_cmds(args: &CommandArguments, cmds: Vec<Command>, title: &st
         }
     }
     {
-        args.event_waiter.register_event(ReactionEvent::new(ResponseAccess::Everyone, 100, msg, update_help, args));
+        args.event_waiter.register_event(ReactionEvent::new(ResponseAccess::Everyone, 100, &msg, update_help, args));
 
         /*args.event_waiter.register_event(ReactionEvent {
             access: ResponseAccess::Everyone,
@@ -69,6 +73,7 @@ pub(super) fn print_cmds(args: &CommandArguments, cmds: Vec<Command>, title: &st
             callback: update_help,
         })*/
     }
+    Ok(())
 }
 
 //noinspection DuplicatedCode
@@ -81,10 +86,11 @@ pub fn update_help(ctx: &Context, event: &mut ReactionEvent, reaction: &Reaction
                     continue;
                 }
                 embed.title("Help ".to_string() + &cmd.category.to_string());
-                if cmd.help_page == "" {
-                    s.push_str(&format!("``{}{}`` => {}\n", event.settings.default_prefix, cmd.key, cmd.description));
+                let usage = cmd.usage();
+                if usage.is_empty() {
+                    s.push_str(&format!("``{}{}`` => {}\n", event.prefix, cmd.key, cmd.description));
                 } else {
-                    s.push_str(&format!("``{}{} {}`` => {}\n", event.settings.default_prefix, cmd.key, cmd.help_page, cmd.description));
+                    s.push_str(&format!("``{}{} {}`` => {}\n", event.prefix, cmd.key, usage, cmd.description));
                 }
             }
             embed.description(s);
@@ -1,16 +1,74 @@
-use crate::command_framework::{Command, CommandManager};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::model::permissions::Permissions;
+use serenity::prelude::{Mutex, RwLock};
+
+use crate::command_framework::{ArgumentSpec, Command, CommandManager, Cooldown, CooldownBucket, Requirements};
 use crate::commands::category::Category;
-use crate::commands::image_gen::print_template_features;
+use crate::commands::image_gen::template_params;
 use crate::util::image::ImageStorage;
 
+pub(super) const IMAGE_COOLDOWNS: &[Cooldown] = &[Cooldown::new(CooldownBucket::User, 2, 10), Cooldown::new(CooldownBucket::Guild, 10, 30)];
+// DOWNLOADING AVATARS AND RENDERING TAKES A WHILE
+pub(super) const IMAGE_TIMEOUT: Duration = Duration::from_secs(30);
+pub(super) const IMAGE_REQUIREMENTS: Requirements = Requirements::bot_permissions(Permissions::EMBED_LINKS.union(Permissions::ATTACH_FILES));
+const NSFW_IMAGE_REQUIREMENTS: Requirements = Requirements {
+    nsfw_only: true,
+    ..IMAGE_REQUIREMENTS
+};
+
+lazy_static! {
+    // COMMANDS NEED STATIC STRINGS. EVERYTHING IS LEAKED ONCE AND REUSED ON EVERY RELOAD, SO ONLY NEW OR CHANGED TEMPLATES COST MEMORY
+    static ref INTERNED_STRS: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+    static ref INTERNED_PARAMS: Mutex<Vec<&'static [ArgumentSpec]>> = Mutex::new(Vec::new());
+}
+
+pub(super) fn intern(s: &str) -> &'static str {
+    let mut strs = INTERNED_STRS.lock();
+    if let Some(interned) = strs.get(s) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(s.to_owned().into_boxed_str());
+    strs.insert(interned);
+    interned
+}
+
+fn intern_params(params: Vec<ArgumentSpec>) -> &'static [ArgumentSpec] {
+    let mut interned = INTERNED_PARAMS.lock();
+    if let Some(p) = interned.iter().find(|p| **p == params.as_slice()) {
+        return p;
+    }
+    let p: &'static [ArgumentSpec] = Box::leak(params.into_boxed_slice());
+    interned.push(p);
+    p
+}
+
 pub fn register_images(command_manager: &mut CommandManager, images: &ImageStorage) {
     for key in images.get_all_keys() {
         command_manager.register_command(Command {
-            key: Box::leak(key.clone().into_boxed_str()),
-            description: Box::leak(format!("Generates a new {} image", &key).into_boxed_str()),
-            help_page: Box::leak(format!("{}", print_template_features(images, &key)).into_boxed_str()),
+            key: intern(&key),
+            aliases: &[],
+            description: intern(&format!("Generates a new {} image", &key)),
+            params: intern_params(template_params(images, &key)),
             category: Category::GeneratedImage,
+            cooldowns: IMAGE_COOLDOWNS,
+            requirements: if images.is_nsfw(&key) { &NSFW_IMAGE_REQUIREMENTS } else { &IMAGE_REQUIREMENTS },
+            timeout: IMAGE_TIMEOUT,
             func: super::image_gen,
         });
     }
+}
+
+/// Replaces the commands of the old templates with the ones of the new templates and swaps the templates.
+/// Commands which are already running keep the old templates
+pub fn reload_images(command_manager: &RwLock<CommandManager>, slot: &RwLock<Arc<ImageStorage>>, images: ImageStorage) {
+    let mut command_manager = command_manager.write();
+    let mut slot = slot.write();
+
+    let old_keys = slot.get_all_keys();
+    command_manager.unregister_commands(|c| old_keys.iter().any(|k| k == c.key));
+    register_images(&mut command_manager, &images);
+    *slot = Arc::new(images);
 }
\ No newline at end of file
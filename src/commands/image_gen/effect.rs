@@ -0,0 +1,69 @@
+use image::{FilterType, GenericImageView};
+
+use crate::command_framework::prelude::*;
+use crate::commands::image_gen::command_gen::{IMAGE_COOLDOWNS, IMAGE_REQUIREMENTS, IMAGE_TIMEOUT};
+use crate::commands::image_gen::resolver::ImageResolver;
+use crate::util::image::effects;
+use crate::util::image::Template;
+
+// BIGGER ATTACHMENTS ARE SCALED DOWN. THE EFFECTS ARE SLOW ON BIG IMAGES
+const MAX_SIZE: u32 = 512;
+
+pub static DEEPFRY_COMMAND: Command = Command {
+    key: "deepfry",
+    aliases: &["fry"],
+    description: "Deep-fries an avatar or an image",
+    params: &[ArgumentSpec::optional("image", ArgumentKind::Image)],
+    category: Category::GeneratedImage,
+    cooldowns: IMAGE_COOLDOWNS,
+    requirements: &IMAGE_REQUIREMENTS,
+    timeout: IMAGE_TIMEOUT,
+    func: effect_command,
+};
+
+pub static DANK_COMMAND: Command = Command {
+    key: "dank",
+    aliases: &["mlg"],
+    description: "Makes an avatar or an image dank",
+    params: &[ArgumentSpec::optional("image", ArgumentKind::Image)],
+    category: Category::GeneratedImage,
+    cooldowns: IMAGE_COOLDOWNS,
+    requirements: &IMAGE_REQUIREMENTS,
+    timeout: IMAGE_TIMEOUT,
+    func: effect_command,
+};
+
+/// Applies the effect preset with the name of the command to an image. See ImageResolver for where the image comes from
+fn effect_command(args: CommandArguments) -> CommandResult {
+    let _permit = match super::RENDER_LIMIT.try_acquire() {
+        Some(s) => s,
+        None => {
+            let _ = args.m.reply(args.ctx, "I'm busy generating other images right now. Please try again in a few seconds!");
+            return Ok(MarkAsFailed);
+        }
+    };
+
+    let images = Arc::clone(&args.image.read());
+    let preset = match images.effect_preset(args.command.key) {
+        Some(s) => s,
+        None => {
+            let _ = args.m.reply(args.ctx, "Sorry, this effect is not available right now");
+            return Ok(MarkAsFailed);
+        }
+    };
+
+    // DEFAULTS TO THE AVATAR OF THE AUTHOR, SO THERE IS ALWAYS AN IMAGE
+    let url = ImageResolver::new(&args).resolve("image", true).unwrap_or_else(|| super::avatar_url(&args.m.author));
+
+    let img = super::download_image(&url)?;
+    let img = img.map(|frame| {
+        if frame.width() > MAX_SIZE || frame.height() > MAX_SIZE {
+            frame.resize(MAX_SIZE, MAX_SIZE, FilterType::Triangle)
+        } else {
+            frame.clone()
+        }
+    });
+    let img = effects::apply_frames(&img, preset);
+
+    super::send_image(&args, &Template::from_image(args.command.key.to_owned(), img))
+}
@@ -1,157 +1,131 @@
-use std::io::Read;
-
-use reqwest::Response;
 use serenity::http::AttachmentType;
+use serenity::model::user::User;
 
 use crate::command_framework::prelude::*;
 use crate::util::image::{ImageStorage, Template};
+use crate::util::image::animation::{ImageFrames, OutputTooLarge};
+use crate::util::image::fetch;
+use crate::util::concurrency::ConcurrencyLimit;
 use crate::util::image::feature::FeatureType;
 
+use self::resolver::ImageResolver;
+
 pub mod command_gen;
+pub mod effect;
+pub mod reload;
+mod resolver;
 
-fn image_gen(args: CommandArguments) -> CommandResult {
-    let mut split = args.m.content.split_whitespace();
+// RENDERING IS EXPENSIVE. DON'T LET A BURST OF IMAGE COMMANDS BLOCK ALL SHARD THREADS
+static RENDER_LIMIT: ConcurrencyLimit = ConcurrencyLimit::new(4);
 
-    let key = split.next().unwrap();
-    let required_features = args.image.get_required_features(key).unwrap();
-    let user_images_count = required_features.iter().filter(|f| f.kind == FeatureType::UserImage).count();
-    let mut template = args.image.start_building(key).unwrap();
+fn image_gen(args: CommandArguments) -> CommandResult {
+    let _permit = match RENDER_LIMIT.try_acquire() {
+        Some(s) => s,
+        None => {
+            let _ = args.m.reply(args.ctx, "I'm busy generating other images right now. Please try again in a few seconds!");
+            return Ok(MarkAsFailed);
+        }
+    };
+
+    let images = Arc::clone(&args.image.read());
+    let key = args.command.key;
+    // THE TEMPLATE MAY HAVE BEEN REMOVED BY A RELOAD SINCE THE COMMAND WAS FOUND
+    let (required_features, mut template) = match (images.get_required_features(key), images.start_building(key)) {
+        (Some(f), Some(t)) => (f, t),
+        _ => {
+            let _ = args.m.reply(args.ctx, "Sorry, this template is not available anymore");
+            return Ok(MarkAsFailed);
+        }
+    };
 
-    let http = reqwest::Client::new();
+    let mut resolver = ImageResolver::new(&args);
 
-    let mentions = super::util::parse_mentions(&args.m.content);
-    for (i, feature) in required_features.into_iter().enumerate() {
+    for feature in required_features.into_iter() {
         match feature.kind {
-            FeatureType::Image => { unwrap_cmd_err!(args.command, template.set_image(&feature.key), "could not set image"); }
-            FeatureType::SplitText => {
-                let mut t = String::new();
-                for mut next in split.by_ref() {
-                    let mut stop = false;
-                    if next.ends_with(",") {
-                        next = &next[..next.len() - 1];
-                        stop = true;
-                    }
-
-                    t.push_str(next);
-                    t.push_str(" ");
-
-                    if stop {
-                        break;
-                    }
-                }
-                unwrap_cmd_err!(args.command, template.set_text(&feature.key, t), "could not set split text");
+            FeatureType::Image => { unwrap_cmd_err!(Internal, template.set_image(&feature.key), "I could not set an image of the template"); }
+            FeatureType::SplitText | FeatureType::Text => {
+                let t = args.params.text(&feature.key).unwrap_or_default().to_owned();
+                unwrap_cmd_err!(Internal, template.set_text(&feature.key, t), "I could not set a text of the template");
             }
             FeatureType::UserImage => {
-                let user = if i == 0 && feature.default_user.unwrap_or_default() == true && mentions.len() != user_images_count {
-                    args.m.author.clone()
-                } else {
-                    // OK PARSE NEXT MENTION
-                    let next = match split.by_ref().next() {
-                        Some(s) => s,
-                        None => {
-                            let _ = args.m.channel_id.send_message(args.ctx, |mb| {
-                                mb.embed(|mut eb| {
-                                    eb.title(format!(r#"Meme Maker: "{}""#, key));
-                                    eb.description(format!("Please specify following parameters: \n``{}{} {}``", args.settings.default_prefix, key, print_template_features(&args.image, key)));
-
-                                    super::util::add_timestamp(&mut eb);
-                                    super::util::add_footer(&mut eb, &args);
-                                    eb
-                                })
-                            });
-                            return Ok(MarkAsWrongUsage);
-                        }
-                    };
-
-                    let mention = match super::util::parse_mentions(next).get(0) {
-                        Some(s) => s.clone(),
-                        None => return Ok(PrintUsage)
-                    };
-
-                    match args.m.mentions.iter().find(|m| m.id.0 == mention.parse::<u64>().unwrap()) {
-                        Some(s) => s.clone(),
-                        None => {
-                            let _ = args.m.reply(args.ctx, "User not found");
-                            return Ok(MarkAsFailed);
-                        }
-                    }
-                };
-
-                let mut avatar_url = match user.static_avatar_url() {
+                // OPTIONAL USER IMAGES DEFAULT TO THE AUTHOR
+                let url = match resolver.resolve(&feature.key, feature.default_user.unwrap_or_default()) {
                     Some(s) => s,
                     None => {
-                        let _ = args.m.reply(args.ctx, "Sorry at least one of your specified users(or yourself) don't have a valid avatar");
+                        let _ = args.m.reply(args.ctx, format!("Sorry, I could not find an image for ``{}``. Mention a user, attach an image or add an image URL!", feature.key));
                         return Ok(MarkAsFailed);
                     }
                 };
-
-                avatar_url = avatar_url.replace(".webp?size=1024", ".png?size=128"); // IMAGE LIB DOES NOT FULLY SUPPORT .WEBP
-
-                let url = unwrap_cmd_err!(args.command, reqwest::Url::parse(&avatar_url), "could not build avatar url");
-                let req = unwrap_cmd_err!(args.command, http.get(url).build(), "could not build avatar command");
-                let mut res: Response = unwrap_cmd_err!(args.command, http.execute(req), "could not execute avatar request");
-                let mut buf = Vec::new();
-                unwrap_cmd_err!(args.command, res.read_to_end(&mut buf), "could not read avatar request's body");
-                let img = unwrap_cmd_err!(args.command, image::load_from_memory(&buf), "could not transform avatar image to DynamicImage struct");
-                unwrap_cmd_err!(args.command, template.set_user_image(&feature.key, img), "could not set user image");
-            }
-            FeatureType::Text => {
-                let mut t = String::new();
-
-                for next in split.by_ref() {
-                    t.push_str(next);
-                    t.push_str(" ");
-                }
-                unwrap_cmd_err!(args.command, template.set_text(&feature.key, t), "could not set text");
+                let img = download_image(&url)?;
+                unwrap_cmd_err!(Internal, template.set_user_image(&feature.key, img), "I could not set an image of the template");
             }
         }
     }
 
-    let template: Template = unwrap_cmd_err!(args.command, template.build(), "could not build template");
-    let img_buf: Vec<u8> = unwrap_cmd_err!(args.command, template.apply(), "could not apply template");
+    let template: Template = unwrap_cmd_err!(Internal, template.build(), "I could not build the template");
+    send_image(&args, &template)
+}
+
+/// The URL of a small version of the avatar. Users without avatar have a default avatar
+fn avatar_url(user: &User) -> String {
+    user.face()
+        .replace(".webp?size=1024", ".png?size=128") // IMAGE LIB DOES NOT FULLY SUPPORT .WEBP
+        .replace(".gif?size=1024", ".gif?size=128") // ANIMATED AVATARS
+}
+
+/// The error tells the user what is wrong with the image
+fn download_image(url: &str) -> Result<ImageFrames, CommandError> {
+    fetch::fetch_image(url).map_err(|e| {
+        if e.is_bad_input() {
+            CommandError::BadInput { message: format!("Sorry, {}!", e), source: Some(Box::new(e)) }
+        } else {
+            CommandError::UpstreamUnavailable { message: e.to_string(), source: Some(Box::new(e)) }
+        }
+    })
+}
 
-    let _ = args.m.channel_id.send_message(args.ctx, |mb| {
-        mb.embed(|mut eb| {
-            eb.image("attachment://make_image.png");
-            super::util::add_timestamp(&mut eb);
-            super::util::add_footer(&mut eb, &args);
+/// Renders the template and sends it as embed
+fn send_image(args: &CommandArguments, template: &Template) -> CommandResult {
+    let img_buf: Vec<u8> = match template.apply() {
+        Err(ref e) if e.is::<OutputTooLarge>() => return Err(CommandError::bad_input("The GIF would be too large to upload. Please try a shorter or smaller animation")),
+        result => unwrap_cmd_err!(Internal, result, "I could not render the image")
+    };
+    let filename = if template.is_animated() { "make_image.gif" } else { "make_image.png" };
+
+    unwrap_cmd_err!(DiscordApi, args.m.channel_id.send_message(args.ctx, |mb| {
+        mb.embed(|eb| {
+            eb.image(format!("attachment://{}", filename));
+            super::util::add_timestamp(eb);
+            super::util::add_footer(eb, args);
             eb
         });
-        mb.add_file(AttachmentType::Bytes { data: img_buf.into(), filename: "make_image.png".to_string() });
+        mb.add_file(AttachmentType::Bytes { data: img_buf.into(), filename: filename.to_string() });
         mb
-    });
+    }), "I could not upload the image");
 
     Ok(MarkAsSucceeded)
 }
 
-fn print_template_features(images: &ImageStorage, template_key: &str) -> String {
-    let mut buf = "".to_owned();
+/// Builds the argument specs of a template's command out of the template's features
+fn template_params(images: &ImageStorage, template_key: &str) -> Vec<ArgumentSpec> {
+    let mut params = Vec::new();
 
     let req_features = images.get_required_features(template_key).unwrap();
     for f in req_features {
-        match f.kind {
-            FeatureType::UserImage => {
-                if f.default_user.unwrap_or_default() == true {
-                    buf.push_str(&format!("[<{}:@User>] ", f.key));
-                } else {
-                    buf.push_str(&format!("<{}:@User> ", f.key));
-                }
-            }
-            FeatureType::SplitText => {
-                buf.push_str(&format!("<{}:Text>, ", f.key));
-            }
-            FeatureType::Text => {
-                buf.push_str(&format!("<{}:Text> ", f.key));
-            }
-            FeatureType::Image => {}
+        let kind = match f.kind {
+            FeatureType::UserImage => ArgumentKind::Image,
+            FeatureType::SplitText => ArgumentKind::Delimited,
+            FeatureType::Text => ArgumentKind::Rest,
+            FeatureType::Image => continue
+        };
+        let name = command_gen::intern(&f.key);
+
+        if f.default_user.unwrap_or_default() {
+            params.push(ArgumentSpec::optional(name, kind));
+        } else {
+            params.push(ArgumentSpec::new(name, kind));
         }
     }
 
-    if buf.ends_with(", ") {
-        buf.drain((buf.len() - 2)..);
-    }
-    if buf.ends_with(" ") { buf.drain(buf.len() - 1..); }
-
-
-    buf
-}
\ No newline at end of file
+    params
+}
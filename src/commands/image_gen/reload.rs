@@ -0,0 +1,67 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::command_framework::prelude::*;
+use crate::commands::image_gen::command_gen;
+use crate::util::image::ImageStorage;
+
+// DISCORD'S LIMIT IS 2000 CHARS. LEAVES ROOM FOR THE SUMMARY
+const MAX_WARNINGS_LENGTH: usize = 1500;
+
+pub static RELOAD_TEMPLATES_COMMAND: Command = Command {
+    key: "reloadtemplates",
+    aliases: &[],
+    description: "Reloads the image templates",
+    params: &[],
+    category: Category::Admin,
+    cooldowns: &[],
+    requirements: &Requirements {
+        owner_only: true,
+        ..Requirements::NONE
+    },
+    // ALL FONTS AND BASE IMAGES ARE READ AGAIN
+    timeout: Duration::from_secs(60),
+    func: reload_templates_command,
+};
+
+/// Parses the templates again and swaps them with the old ones. If the templates cannot be loaded at all, the old ones stay
+fn reload_templates_command(args: CommandArguments) -> CommandResult {
+    let images = match ImageStorage::load(Path::new("./templates/")) {
+        Ok(k) => k,
+        Err(e) => {
+            warn!("TEMPLATE RELOAD: could not load the templates: {}", e);
+            let _ = args.m.reply(args.ctx, format!("Could not load the templates: {}. The old templates are still in use", e));
+            return Ok(MarkAsFailed);
+        }
+    };
+
+    let new_keys = images.get_all_keys();
+    let old_keys = args.image.read().get_all_keys();
+    let added = new_keys.iter().filter(|k| !old_keys.contains(k)).count();
+    let removed = old_keys.iter().filter(|k| !new_keys.contains(k)).count();
+    let warnings = images.warnings().to_vec();
+
+    command_gen::reload_images(&args.handler, &args.image, images);
+    info!("TEMPLATE RELOAD: reloaded {} templates ({} new, {} removed)", new_keys.len(), added, removed);
+
+    match args.ctx.http.get_current_application_info() {
+        Ok(application) => crate::register_interactions(&args.ctx.http.token, &application, &args.handler.read()),
+        Err(e) => warn!("TEMPLATE RELOAD: could not fetch application info to update the slash commands: {}", e)
+    }
+
+    let mut s = format!("Reloaded {} templates ({} new, {} removed)", new_keys.len(), added, removed);
+    if !warnings.is_empty() {
+        s.push_str(&format!("\n{} warning(s):\n```\n{}\n```", warnings.len(), truncate(&warnings.join("\n"))));
+    }
+    let _ = args.m.reply(args.ctx, s);
+    Ok(MarkAsSucceeded)
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() <= MAX_WARNINGS_LENGTH {
+        return s.to_owned();
+    }
+    let mut truncated: String = s.chars().take(MAX_WARNINGS_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
\ No newline at end of file
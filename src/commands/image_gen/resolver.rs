@@ -0,0 +1,106 @@
+use crate::command_framework::ImageArgument;
+use crate::command_framework::prelude::*;
+
+// THE MOST RECENT IMAGE IS SEARCHED IN THIS MANY MESSAGES BEFORE THE COMMAND
+const RECENT_MESSAGES: u64 = 25;
+
+/// Finds the image of an image argument. Tries in this order:
+/// 1. an image attached to the command
+/// 2. an image URL or the avatar of a mentioned user
+/// 3. the image of the message which is replied to
+/// 4. a custom emoji
+/// 5. the avatar of the author, if the argument defaults to the author
+/// 6. the most recent image in the channel
+///
+/// Every attachment, the replied image and the recent image are used for one argument only
+pub struct ImageResolver<'a> {
+    args: &'a CommandArguments<'a>,
+    // THE NEXT ONE IS THE LAST ONE
+    attachments: Vec<String>,
+    reply_used: bool,
+    recent_used: bool,
+}
+
+impl<'a> ImageResolver<'a> {
+    pub fn new(args: &'a CommandArguments<'a>) -> Self {
+        let mut attachments: Vec<String> = args.m.attachments.iter()
+            .filter(|a| a.width.is_some()) // ONLY IMAGES HAVE A WIDTH
+            .map(|a| a.url.clone())
+            .collect();
+        attachments.reverse();
+        ImageResolver {
+            args,
+            attachments,
+            reply_used: false,
+            recent_used: false,
+        }
+    }
+
+    /// The URL of the image. None if there is no image anywhere
+    pub fn resolve(&mut self, name: &str, default_user: bool) -> Option<String> {
+        if let Some(url) = self.attachments.pop() {
+            return Some(url);
+        }
+
+        let argument = self.args.params.image(name);
+        match argument {
+            Some(ImageArgument::Url(url)) => return Some(url.clone()),
+            Some(ImageArgument::User(user)) => return Some(super::avatar_url(user)),
+            _ => {}
+        }
+
+        if !self.reply_used {
+            self.reply_used = true;
+            if let Some(url) = self.reply_image() {
+                return Some(url);
+            }
+        }
+
+        if let Some(ImageArgument::Emoji(url)) = argument {
+            return Some(url.clone());
+        }
+
+        if default_user {
+            return Some(super::avatar_url(&self.args.m.author));
+        }
+
+        if !self.recent_used {
+            self.recent_used = true;
+            return self.recent_image();
+        }
+        None
+    }
+
+    fn reply_image(&self) -> Option<String> {
+        let reference = self.args.m.message_reference.as_ref()?;
+        match reference.channel_id.message(self.args.ctx, reference.message_id?) {
+            Ok(m) => message_image(&m),
+            Err(e) => {
+                debug!("IMAGE RESOLVER: could not fetch the replied message: {}", e);
+                None
+            }
+        }
+    }
+
+    fn recent_image(&self) -> Option<String> {
+        // NEWEST FIRST
+        match self.args.m.channel_id.messages(self.args.ctx, |r| r.before(self.args.m.id).limit(RECENT_MESSAGES)) {
+            Ok(messages) => messages.iter().find_map(message_image),
+            Err(e) => {
+                debug!("IMAGE RESOLVER: could not fetch the recent messages: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The first attached or embedded image of the message
+fn message_image(m: &Message) -> Option<String> {
+    if let Some(a) = m.attachments.iter().find(|a| a.width.is_some()) {
+        return Some(a.url.clone());
+    }
+    m.embeds.iter().find_map(|e| {
+        e.image.as_ref().map(|i| i.url.clone())
+            .or_else(|| e.thumbnail.as_ref().map(|t| t.url.clone()))
+    })
+}
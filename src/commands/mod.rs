@@ -4,4 +4,6 @@ pub mod animal;
 pub mod about;
 pub mod fun;
 pub mod image_gen;
-pub mod category;
\ No newline at end of file
+pub mod category;
+pub mod admin;
+pub mod stats;
\ No newline at end of file
@@ -0,0 +1,122 @@
+use serenity::model::id::GuildId;
+use serenity::utils::Colour;
+
+use crate::command_framework::prelude::*;
+use crate::util::analytics;
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 365;
+const TOP_COMMANDS: usize = 10;
+
+pub static STATS_COMMAND: Command = Command {
+    key: "stats",
+    aliases: &["usage"],
+    description: "Shows the most used commands on this server and how often they failed. The window defaults to 7 days (e.g. 1d, 30d)",
+    params: &[ArgumentSpec::optional("window", ArgumentKind::Quoted)],
+    category: Category::Misc,
+    cooldowns: &[Cooldown::new(CooldownBucket::Channel, 2, 30)],
+    requirements: &Requirements {
+        bot_permissions: Permissions::EMBED_LINKS,
+        guild_only: true,
+        ..Requirements::NONE
+    },
+    timeout: DEFAULT_TIMEOUT,
+    func: stats_command,
+};
+
+pub static GLOBAL_STATS_COMMAND: Command = Command {
+    key: "globalstats",
+    aliases: &[],
+    description: "Shows the most used commands on all servers and how often they failed. The window defaults to 7 days (e.g. 1d, 30d)",
+    params: &[ArgumentSpec::optional("window", ArgumentKind::Quoted)],
+    category: Category::Admin,
+    cooldowns: &[],
+    requirements: &Requirements {
+        bot_permissions: Permissions::EMBED_LINKS,
+        owner_only: true,
+        ..Requirements::NONE
+    },
+    timeout: DEFAULT_TIMEOUT,
+    func: global_stats_command,
+};
+
+fn stats_command(args: CommandArguments) -> CommandResult {
+    let guild_id = args.require_guild()?.id;
+    show_stats(&args, Some(guild_id))
+}
+
+fn global_stats_command(args: CommandArguments) -> CommandResult {
+    show_stats(&args, None)
+}
+
+/// The stats of one guild or of all guilds if `guild_id` is None
+fn show_stats(args: &CommandArguments, guild_id: Option<GuildId>) -> CommandResult {
+    let days = match args.params.text("window") {
+        Some(w) => match parse_window(w) {
+            Some(d) => d,
+            None => return Err(CommandError::BadInput {
+                message: format!("``{}`` is not a valid window! Use something between 1d and {}d", w, MAX_DAYS),
+                source: None,
+            })
+        },
+        None => DEFAULT_DAYS
+    };
+
+    let usages = unwrap_cmd_err!(UpstreamUnavailable, analytics::usage(days, guild_id), "I could not load the statistics");
+    let total: i64 = usages.iter().map(|u| u.uses).sum();
+    let failures: i64 = usages.iter().map(|u| u.failures).sum();
+
+    let mut s = String::new();
+    for (i, usage) in usages.iter().take(TOP_COMMANDS).enumerate() {
+        s.push_str(&format!("**{}.** ``{}{}``: {} use(s), {:.1}% failed", i + 1, args.prefix, usage.command, usage.uses, usage.failure_rate() * 100.0));
+        if let Some(latency) = usage.average_latency() {
+            s.push_str(&format!(", {}ms on average", latency.as_millis()));
+        }
+        s.push('\n');
+    }
+    if s.is_empty() {
+        s.push_str("No commands have been used yet");
+    }
+
+    unwrap_cmd_err!(DiscordApi, args.m.channel_id.send_message(args.ctx, |cb| {
+        cb.embed(|eb| {
+            eb.title(format!("Command usage {} in the last {} day(s)", if guild_id.is_some() { "on this server" } else { "on all servers" }, days));
+            eb.description(s);
+            eb.color(Colour::from_rgb(67, 181, 129));
+            eb.footer(|fb| {
+                let rate = if total == 0 { 0.0 } else { failures as f64 / total as f64 * 100.0 };
+                fb.text(format!("{} command(s) used, {:.1}% failed", total, rate))
+            });
+            eb
+        });
+        cb
+    }), "I could not send the statistics");
+    Ok(MarkAsSucceeded)
+}
+
+/// "7d" or "7" => 7
+fn parse_window(s: &str) -> Option<i64> {
+    let s = s.trim().to_lowercase();
+    let days: i64 = s.strip_suffix('d').unwrap_or(&s).parse().ok()?;
+    if !(1..=MAX_DAYS).contains(&days) {
+        return None;
+    }
+    Some(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        assert_eq!(parse_window("7d"), Some(7));
+        assert_eq!(parse_window(" 30D "), Some(30));
+        assert_eq!(parse_window("1"), Some(1));
+        assert_eq!(parse_window(&format!("{}d", MAX_DAYS)), Some(MAX_DAYS));
+        assert_eq!(parse_window("0d"), None);
+        assert_eq!(parse_window(&format!("{}d", MAX_DAYS + 1)), None);
+        assert_eq!(parse_window("-3d"), None);
+        assert_eq!(parse_window("a week"), None);
+    }
+}
\ No newline at end of file
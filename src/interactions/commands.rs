@@ -0,0 +1,128 @@
+use std::error;
+
+use serenity::model::id::GuildId;
+
+use crate::command_framework::{ArgumentKind, ArgumentSpec, Command};
+
+use super::payload::option_name;
+
+// https://discord.com/developers/docs/interactions/slash-commands#applicationcommandoptiontype
+const OPTION_STRING: u8 = 3;
+const OPTION_INTEGER: u8 = 4;
+const OPTION_USER: u8 = 6;
+
+const MAX_COMMANDS: usize = 100;
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_DESCRIPTION_LENGTH: usize = 100;
+
+/// https://discord.com/developers/docs/interactions/slash-commands#applicationcommand
+#[derive(Clone, Debug, Serialize)]
+pub struct ApplicationCommand {
+    pub name: String,
+    pub description: String,
+    pub options: Vec<ApplicationCommandOption>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ApplicationCommandOption {
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
+/// Generates the application commands out of the registered commands. Image templates are included,
+/// their options are derived from the template's features. Aliases and owner-only commands are skipped
+pub fn build_commands(commands: &[Command]) -> Vec<ApplicationCommand> {
+    let mut ret = Vec::new();
+
+    for cmd in commands.iter() {
+        if cmd.requirements.owner_only {
+            continue;
+        }
+        let name = cmd.key.to_lowercase();
+        if name.chars().count() > MAX_NAME_LENGTH || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+            warn!(r#"INTERACTIONS: command "{}" is not a valid application command name. Skipping it"#, cmd.key);
+            continue;
+        }
+        if ret.len() >= MAX_COMMANDS {
+            warn!("INTERACTIONS: more than {} commands. Skipping the rest", MAX_COMMANDS);
+            break;
+        }
+
+        ret.push(ApplicationCommand {
+            name,
+            description: truncate(cmd.description),
+            options: build_options(cmd.params),
+        });
+    }
+    ret
+}
+
+/// Required options have to be in front of optional ones
+fn build_options(specs: &[ArgumentSpec]) -> Vec<ApplicationCommandOption> {
+    let mut options: Vec<ApplicationCommandOption> = specs.iter().map(|spec| {
+        let kind = match spec.kind {
+            ArgumentKind::User | ArgumentKind::Member => OPTION_USER,
+            ArgumentKind::Integer => OPTION_INTEGER,
+            // SLASH COMMANDS CANNOT HAVE ATTACHMENTS. IMAGES ARE PASSED AS URL, EMOJI OR MENTION
+            ArgumentKind::Quoted | ArgumentKind::Rest | ArgumentKind::Delimited | ArgumentKind::List | ArgumentKind::Image => OPTION_STRING,
+        };
+        ApplicationCommandOption {
+            kind,
+            name: option_name(spec.name),
+            description: truncate(&format!("{}: {}", spec.name, spec.kind.label())),
+            // THE COMMAND FINDS AN IMAGE ON ITS OWN IF IT IS LEFT OUT
+            required: !spec.optional && spec.kind != ArgumentKind::Image,
+        }
+    }).collect();
+    options.sort_by_key(|o| !o.required);
+    options
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() <= MAX_DESCRIPTION_LENGTH {
+        return s.to_owned();
+    }
+    let mut s: String = s.chars().take(MAX_DESCRIPTION_LENGTH - 3).collect();
+    s.push_str("...");
+    s
+}
+
+/// Overwrites all application commands of the bot. If a guild is given the commands are only registered on that guild
+/// (guild commands are updated instantly which is helpful for testing)
+pub fn register(application_id: u64, token: &str, guild: Option<GuildId>, commands: &[ApplicationCommand]) -> Result<(), Box<dyn error::Error>> {
+    let url = match guild {
+        Some(g) => format!("https://discord.com/api/v8/applications/{}/guilds/{}/commands", application_id, g.0),
+        None => format!("https://discord.com/api/v8/applications/{}/commands", application_id)
+    };
+
+    let mut res = reqwest::Client::new().put(url.as_str()).header("Authorization", token).json(commands).send()?;
+    if !res.status().is_success() {
+        return Err(format!("discord responded with {}: {}", res.status(), res.text()?).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_options_first() {
+        let specs = [ArgumentSpec::optional("lover", ArgumentKind::User), ArgumentSpec::new("loved", ArgumentKind::User)];
+        let options = build_options(&specs);
+        assert_eq!(options[0].name, "loved");
+        assert!(options[0].required);
+        assert_eq!(options[1].name, "lover");
+        assert_eq!(options[1].kind, OPTION_USER);
+    }
+
+    #[test]
+    fn truncates_descriptions() {
+        let long = "a".repeat(150);
+        assert_eq!(truncate(&long).chars().count(), MAX_DESCRIPTION_LENGTH);
+        assert_eq!(truncate("short"), "short");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::error;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::id::MessageId;
+use serenity::model::misc::Mentionable;
+use serenity::prelude::Mutex;
+
+use crate::command_framework::command_handler::{check_requirements, execute, report_argument_error};
+use crate::command_framework::GuildContext;
+use crate::Handler;
+
+use self::payload::{APPLICATION_COMMAND, Interaction};
+
+pub mod commands;
+pub mod payload;
+
+const API_URL: &str = "https://discord.com/api/v8";
+const PREFIX: &str = "/";
+// https://discord.com/developers/docs/interactions/slash-commands#interaction-response-interactionresponsetype
+const CHANNEL_MESSAGE_WITH_SOURCE: u8 = 4;
+// THE TOKEN OF AN INTERACTION CAN BE USED FOR THIS LONG
+const TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// The response to an interaction whose message may get reactions
+struct Response {
+    application_id: u64,
+    token: String,
+    created: Instant,
+    // FETCHED WITH THE FIRST REACTION
+    message_id: Option<MessageId>,
+}
+
+lazy_static! {
+    // BY THE ID OF THE MESSAGE BUILT OUT OF THE INTERACTION
+    static ref RESPONSES: Mutex<HashMap<MessageId, Response>> = Mutex::new(HashMap::new());
+}
+
+/// Routes an INTERACTION_CREATE event into the command it belongs to. The command functions get a message
+/// built out of the interaction, so they work the same way as with prefixed messages.
+/// Everything which talks to Discord runs on the command workers, so the shard thread does not wait for HTTP requests
+pub(crate) fn handle_interaction(handler: &Handler, ctx: Context, raw: Value) {
+    let interaction: Interaction = match serde_json::from_value(raw) {
+        Ok(k) => k,
+        Err(e) => {
+            warn!("INTERACTIONS: could not parse interaction: {}", e);
+            return;
+        }
+    };
+    if interaction.kind != APPLICATION_COMMAND {
+        return;
+    }
+
+    let worker_handler = handler.clone();
+    if handler.executor.spawn_task(move || run_interaction(&worker_handler, ctx, interaction)).is_err() {
+        warn!("INTERACTIONS: too many queued commands. Dropping interaction");
+    }
+}
+
+fn run_interaction(handler: &Handler, ctx: Context, interaction: Interaction) {
+    let (data, author) = match (interaction.data.as_ref(), interaction.author()) {
+        (Some(d), Some(a)) => (d, a),
+        _ => return
+    };
+    if author.bot {
+        return;
+    }
+
+    let content = data.command_line();
+    info!("[Interaction] {}: {}", author.name, content);
+
+    let cmd = match handler.ch.read().get_command(&data.name) {
+        Some(c) => c.clone(),
+        None => {
+            let _ = respond(&interaction, "Unknown command! Maybe it has been removed");
+            return;
+        }
+    }; // DROP READ LOCK
+
+    // DISCORD WANTS AN ANSWER WITHIN 3 SECONDS. THE COMMAND ITSELF ANSWERS IN THE CHANNEL LIKE IT DOES FOR MESSAGES
+    if let Err(e) = respond(&interaction, &format!("{} used ``{}``", author.mention(), content)) {
+        error!("INTERACTIONS: could not respond to interaction: {}", e);
+        return;
+    }
+    let application_id = interaction.application_id.as_ref()
+        .and_then(|id| id.parse().ok())
+        .unwrap_or(ctx.cache.read().user.id.0);
+    // THE INTERACTION ID IS A SNOWFLAKE OF THE SAME TIME, SO IT WORKS FOR E.G. "MESSAGES BEFORE THIS ONE", TOO
+    let message_id = MessageId(interaction.id.parse().unwrap_or_default());
+    remember_response(message_id, application_id, &interaction.token);
+    let msg = match payload::to_message(&interaction, message_id, &content) {
+        Ok(k) => k,
+        Err(e) => {
+            error!("INTERACTIONS: could not build message out of interaction: {}", e);
+            return;
+        }
+    };
+
+    let guild = interaction.guild_id.map(|id| GuildContext {
+        id,
+        settings: handler.guild_settings.get(id),
+    });
+    if !check_requirements(handler, &ctx, &msg, &cmd, guild.as_ref()) {
+        return;
+    }
+
+    let member = |user: &serenity::model::user::User| interaction.guild_id.and_then(|g| g.member(&ctx, user.id).ok());
+    let params = match payload::parse_options(cmd.params, data, &member) {
+        Ok(k) => k,
+        Err(e) => {
+            report_argument_error(handler, &ctx, &msg, &cmd, PREFIX, e);
+            return;
+        }
+    };
+    execute(handler, &ctx, &msg, &cmd, guild, params, PREFIX.to_owned());
+}
+
+fn respond(interaction: &Interaction, content: &str) -> Result<(), Box<dyn error::Error>> {
+    let body = json!({
+        "type": CHANNEL_MESSAGE_WITH_SOURCE,
+        "data": {
+            "content": content,
+            "allowed_mentions": {"parse": []}
+        }
+    });
+    let mut res = reqwest::Client::new()
+        .post(format!("{}/interactions/{}/{}/callback", API_URL, interaction.id, interaction.token).as_str())
+        .json(&body)
+        .send()?;
+    if !res.status().is_success() {
+        return Err(format!("discord responded with {}: {}", res.status(), res.text()?).into());
+    }
+    Ok(())
+}
+
+fn remember_response(message_id: MessageId, application_id: u64, token: &str) {
+    let mut responses = RESPONSES.lock();
+    responses.retain(|_, r| r.created.elapsed() < TOKEN_LIFETIME);
+    responses.insert(message_id, Response {
+        application_id,
+        token: token.to_owned(),
+        created: Instant::now(),
+        message_id: None,
+    });
+}
+
+/// The message reactions go to. Messages built out of interactions do not exist, so their reactions go to the
+/// response message. Its ID is only fetched when the first reaction is added
+pub(crate) fn reaction_target(msg: &Message) -> MessageId {
+    let (application_id, token) = match RESPONSES.lock().get(&msg.id) {
+        None => return msg.id,
+        Some(Response { message_id: Some(id), .. }) => return *id,
+        Some(r) => (r.application_id, r.token.clone())
+    };
+
+    match original_message_id(&token, application_id) {
+        Ok(id) => {
+            if let Some(r) = RESPONSES.lock().get_mut(&msg.id) {
+                r.message_id = Some(id);
+            }
+            id
+        }
+        Err(e) => {
+            warn!("INTERACTIONS: could not get response message: {}", e);
+            msg.id
+        }
+    }
+}
+
+fn original_message_id(token: &str, application_id: u64) -> Result<MessageId, Box<dyn error::Error>> {
+    #[derive(Deserialize)]
+    struct OriginalMessage {
+        id: MessageId,
+    }
+
+    let mut res = reqwest::get(format!("{}/webhooks/{}/{}/messages/@original", API_URL, application_id, token).as_str())?;
+    if !res.status().is_success() {
+        return Err(format!("discord responded with {}", res.status()).into());
+    }
+    let original: OriginalMessage = serde_json::from_str(&res.text()?)?;
+    Ok(original.id)
+}
\ No newline at end of file
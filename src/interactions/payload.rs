@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use serenity::model::channel::Message;
+use serenity::model::guild::Member;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use serenity::model::user::User;
+
+use crate::command_framework::{ArgumentKind, ArgumentSpec, Args};
+use crate::command_framework::arguments::{self, ArgumentError, ArgumentValue};
+
+pub const APPLICATION_COMMAND: u8 = 2;
+
+/// https://discord.com/developers/docs/interactions/slash-commands#interaction
+#[derive(Clone, Debug, Deserialize)]
+pub struct Interaction {
+    pub id: String,
+    #[serde(default)]
+    pub application_id: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: u8,
+    pub token: String,
+    #[serde(default)]
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    // SET ON GUILDS
+    #[serde(default)]
+    pub member: Option<InteractionMember>,
+    // SET IN DIRECT MESSAGES
+    #[serde(default)]
+    pub user: Option<User>,
+    #[serde(default)]
+    pub data: Option<InteractionData>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InteractionMember {
+    pub user: User,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InteractionData {
+    pub name: String,
+    #[serde(default)]
+    pub options: Vec<InteractionOption>,
+    #[serde(default)]
+    pub resolved: Resolved,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InteractionOption {
+    pub name: String,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Resolved {
+    // USER ID => USER
+    #[serde(default)]
+    pub users: HashMap<String, User>,
+}
+
+impl Interaction {
+    /// The user who used the interaction
+    pub fn author(&self) -> Option<&User> {
+        match self.member {
+            Some(ref m) => Some(&m.user),
+            None => self.user.as_ref()
+        }
+    }
+}
+
+impl InteractionData {
+    /// Renders the used command like it would have been typed (e.g. "/love @Jane")
+    pub fn command_line(&self) -> String {
+        let mut line = format!("/{}", self.name);
+        for option in self.options.iter() {
+            let value = match option.value {
+                Value::String(ref s) => match self.resolved.users.get(s) {
+                    Some(u) => format!("@{}", u.name),
+                    None => s.clone()
+                },
+                ref other => other.to_string()
+            };
+            line.push(' ');
+            line.push_str(&value);
+        }
+        line
+    }
+}
+
+/// Builds the message the command functions work with. Its id is the id of the interaction's response
+pub fn to_message(interaction: &Interaction, id: MessageId, content: &str) -> Result<Message, serde_json::Error> {
+    let mentions: Vec<&User> = match interaction.data {
+        Some(ref d) => d.resolved.users.values().collect(),
+        None => vec![]
+    };
+    serde_json::from_value(json!({
+        "id": id,
+        "attachments": [],
+        "author": interaction.author(),
+        "channel_id": interaction.channel_id,
+        "content": content,
+        "edited_timestamp": null,
+        "embeds": [],
+        "guild_id": interaction.guild_id,
+        "type": 0,
+        "mention_everyone": false,
+        "mention_roles": [],
+        "mentions": mentions,
+        "pinned": false,
+        "timestamp": Utc::now().to_rfc3339(),
+        "tts": false,
+    }))
+}
+
+/// Turns the argument name into a valid option name (e.g. "new prefix" => "new_prefix")
+pub fn option_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .take(32)
+        .collect()
+}
+
+/// Converts the options of the interaction into the arguments of the command.
+/// `member` resolves users of Member arguments. It returns None if the user is not a member of the guild
+pub fn parse_options(specs: &[ArgumentSpec], data: &InteractionData, member: &dyn Fn(&User) -> Option<Member>) -> Result<Args, ArgumentError> {
+    let mut args = Args::default();
+
+    for spec in specs.iter() {
+        let name = option_name(spec.name);
+        let value = match data.options.iter().find(|o| o.name == name) {
+            Some(o) => &o.value,
+            None if spec.optional || spec.kind == ArgumentKind::Image => continue,
+            None => return Err(ArgumentError::Missing(spec.name))
+        };
+
+        let value = match spec.kind {
+            ArgumentKind::User | ArgumentKind::Member => {
+                let user = value.as_str()
+                    .and_then(|id| data.resolved.users.get(id))
+                    .ok_or_else(|| ArgumentError::Invalid(spec.name, "user not found".to_owned()))?;
+                if spec.kind == ArgumentKind::User {
+                    ArgumentValue::User(user.clone())
+                } else {
+                    match member(user) {
+                        Some(m) => ArgumentValue::Member(m),
+                        None => return Err(ArgumentError::Invalid(spec.name, format!("{} is not a member of this server", user.name)))
+                    }
+                }
+            }
+            ArgumentKind::Integer => match value.as_i64() {
+                Some(i) => ArgumentValue::Integer(i),
+                None => return Err(ArgumentError::Invalid(spec.name, format!("``{}`` is not a number", value)))
+            },
+            ArgumentKind::Quoted | ArgumentKind::Rest | ArgumentKind::Delimited => match value.as_str() {
+                Some(s) if !s.trim().is_empty() => ArgumentValue::Text(s.trim().to_owned()),
+                _ => return Err(ArgumentError::Invalid(spec.name, "must not be empty".to_owned()))
+            },
+            ArgumentKind::List => match value.as_str().map(arguments::split_list) {
+                Some(l) if !l.is_empty() => ArgumentValue::List(l),
+                _ => return Err(ArgumentError::Invalid(spec.name, "must not be empty".to_owned()))
+            },
+            // MENTIONS IN STRING OPTIONS ARE NOT RESOLVED BY DISCORD
+            ArgumentKind::Image => match value.as_str().and_then(|s| arguments::parse_image_link(s.trim())) {
+                Some(i) => ArgumentValue::Image(i),
+                None => return Err(ArgumentError::Invalid(spec.name, "expected an image URL or a custom emoji".to_owned()))
+            },
+        };
+        args.push(spec.name, value);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOCK_INTERACTION: &str = r#"{
+        "id": "786008729715212338",
+        "application_id": "277608782123630593",
+        "type": 2,
+        "token": "A_UNIQUE_TOKEN",
+        "guild_id": "290926798626357999",
+        "channel_id": "645027906669510667",
+        "member": {
+            "user": {"id": "53908232506183680", "username": "Mason", "avatar": null, "discriminator": "1337"},
+            "roles": [],
+            "permissions": "2147483647"
+        },
+        "data": {
+            "id": "771825006014889984",
+            "name": "love",
+            "options": [{"name": "loved", "type": 6, "value": "53908232999999999"}],
+            "resolved": {
+                "users": {"53908232999999999": {"id": "53908232999999999", "username": "Jane", "avatar": null, "discriminator": "0001"}}
+            }
+        }
+    }"#;
+
+    const LOVE_SPECS: &[ArgumentSpec] = &[ArgumentSpec::optional("lover", ArgumentKind::User), ArgumentSpec::new("loved", ArgumentKind::User)];
+
+    fn mock() -> Interaction {
+        serde_json::from_str(MOCK_INTERACTION).expect("could not parse mock interaction")
+    }
+
+    #[test]
+    fn parses_mock_interaction() {
+        let interaction = mock();
+        assert_eq!(interaction.kind, APPLICATION_COMMAND);
+        assert_eq!(interaction.guild_id, Some(GuildId(290926798626357999)));
+        assert_eq!(interaction.author().unwrap().name, "Mason");
+        assert_eq!(interaction.data.unwrap().name, "love");
+    }
+
+    #[test]
+    fn resolves_user_options() {
+        let data = mock().data.unwrap();
+        let args = parse_options(LOVE_SPECS, &data, &|_| None).unwrap();
+        assert!(args.user("lover").is_none());
+        assert_eq!(args.user("loved").unwrap().name, "Jane");
+    }
+
+    #[test]
+    fn missing_required_option() {
+        let mut data = mock().data.unwrap();
+        data.options.clear();
+        match parse_options(LOVE_SPECS, &data, &|_| None) {
+            Err(ArgumentError::Missing("loved")) => {}
+            other => panic!("expected missing argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn builds_message() {
+        let interaction = mock();
+        let content = interaction.data.as_ref().unwrap().command_line();
+        assert_eq!(content, "/love @Jane");
+
+        let msg = to_message(&interaction, MessageId(1), &content).unwrap();
+        assert_eq!(msg.id, MessageId(1));
+        assert_eq!(msg.author.name, "Mason");
+        assert_eq!(msg.channel_id, ChannelId(645027906669510667));
+        assert_eq!(msg.mentions.len(), 1);
+    }
+
+    #[test]
+    fn option_names() {
+        assert_eq!(option_name("new prefix"), "new_prefix");
+        assert_eq!(option_name("Top"), "top");
+    }
+}
\ No newline at end of file
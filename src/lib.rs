@@ -0,0 +1,12 @@
+//! The image templates: parser, renderer and the download of external images.
+//! A library so the bot and the ndm-templates tool share them
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde;
+
+pub mod util {
+    pub mod image;
+}
\ No newline at end of file
@@ -17,8 +17,10 @@ use serenity::model::prelude::*;
 use serenity::prelude::*;
 use simplelog::{CombinedLogger, Config, LevelFilter, SharedLogger, SimpleLogger, TerminalMode, TermLogger, WriteLogger};
 
-use crate::command_framework::CommandManager;
+use crate::command_framework::{CommandManager, CooldownManager, Executor};
 use crate::scheduler::Scheduler;
+use crate::util::analytics::Analytics;
+use crate::util::guild_settings::GuildSettingsManager;
 use crate::util::safe::Safe;
 
 mod util;
@@ -27,6 +29,7 @@ mod scheduler;
 mod command_framework;
 mod commands;
 mod schedules;
+mod interactions;
 
 lazy_static! {
     static ref DB_CLIENT: mongodb::Client = mongodb::Client::with_options(mongodb::options::ClientOptions::builder()
@@ -41,26 +44,39 @@ lazy_static! {
             hostname: std::env::var("MONGO_HOST").expect("MONGO_HOST is missing from environment!"),
             port: Some(std::env::var("MONGO_PORT").expect("MONGO_PORT is missing from environment!").parse().unwrap()),
         }]).build()).unwrap();
+    static ref DB: Database = DB_CLIENT.database(&std::env::var("MONGO_DB").unwrap_or_else(|_| "ndm".to_owned()));
 }
+const COMMAND_WORKERS: usize = 8;
+const MAX_QUEUED_COMMANDS: usize = 64;
+
 pub struct StaticSettings {
     pub default_prefix: String,
     pub start_time: DateTime<Utc>,
+    pub owners: Vec<UserId>,
 }
 
+#[derive(Clone)]
 pub(crate) struct Handler {
     pub ch: Arc<RwLock<CommandManager>>,
     pub safe: Arc<RwLock<Safe>>,
-    pub image: Arc<util::image::ImageStorage>,
+    // SWAPPED WHEN THE TEMPLATES ARE RELOADED
+    pub image: Arc<RwLock<Arc<util::image::ImageStorage>>>,
     pub settings: Arc<StaticSettings>,
     pub eventwaiter: Arc<util::eventwaiter::Eventwaiter>,
+    pub guild_settings: Arc<GuildSettingsManager>,
+    pub cooldowns: Arc<CooldownManager>,
+    pub executor: Arc<Executor>,
+    pub analytics: Arc<Analytics>,
 }
 
 impl Handler {
-    fn new(ch: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, image: Arc<util::image::ImageStorage>, eventwaiter: Arc<util::eventwaiter::Eventwaiter>) -> Handler {
+    fn new(ch: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, image: Arc<RwLock<Arc<util::image::ImageStorage>>>, eventwaiter: Arc<util::eventwaiter::Eventwaiter>, guild_settings: Arc<GuildSettingsManager>, cooldowns: Arc<CooldownManager>, owners: Vec<UserId>) -> Handler {
         let settings = Arc::new(StaticSettings {
             default_prefix: "+".to_string(),
             start_time: Utc::now(),
+            owners,
         });
+        let analytics = Arc::new(Analytics::new());
 
         Handler {
             ch,
@@ -68,6 +84,10 @@ impl Handler {
             image,
             settings,
             eventwaiter,
+            guild_settings,
+            cooldowns,
+            executor: Executor::new(COMMAND_WORKERS, MAX_QUEUED_COMMANDS, Arc::clone(&analytics)),
+            analytics,
         }
     }
 }
@@ -80,11 +100,14 @@ impl Handler {
 
 impl EventHandler for Handler {
     //noinspection RsTraitImplementation
-    fn guild_create(&self, ctx: Context, _guild: Guild, _b: bool) {
+    fn guild_create(&self, ctx: Context, guild: Guild, _b: bool) {
+        // SO THE FIRST COMMAND ON THE GUILD ALREADY USES ITS PREFIX
+        self.guild_settings.preload(guild.id);
         self.update_activity(&ctx);
     }
     //noinspection RsTraitImplementation
-    fn guild_delete(&self, ctx: Context, _incomplete: PartialGuild, _full: Option<Arc<RwLock<Guild>>>) {
+    fn guild_delete(&self, ctx: Context, incomplete: PartialGuild, _full: Option<Arc<RwLock<Guild>>>) {
+        self.guild_settings.invalidate(incomplete.id);
         self.update_activity(&ctx);
     }
 
@@ -92,6 +115,12 @@ impl EventHandler for Handler {
         command_framework::command_handler::handle_command(self, ctx, msg);
     }
 
+    fn unknown(&self, ctx: Context, name: String, raw: serde_json::Value) {
+        if name == "INTERACTION_CREATE" {
+            interactions::handle_interaction(self, ctx, raw);
+        }
+    }
+
     fn reaction_add(&self, context: Context, reaction: Reaction) {
         self.eventwaiter.fire_reaction(context, reaction);
     }
@@ -125,9 +154,13 @@ fn main() {
         discord_token = var("DISCORD_TOKEN").expect("Need DISCORD_TOKEN var");
     }
 
+    info!("Connecting to database...");
+    lazy_static::initialize(&DB_CLIENT);
+
     // LOAD IMAGES
     let templates_path = Path::new("./templates/");
     let images = Arc::new(util::image::ImageStorage::load(templates_path).expect("could not create image storage"));
+    let images = Arc::new(RwLock::new(images));
 
     // REGISTER COMMANDS
     let mut command_handler = CommandManager::new();
@@ -148,8 +181,16 @@ fn main() {
         command_handler.register_command(commands::fun::love::LOVE_COMMAND.clone());
         command_handler.register_command(commands::fun::say::SAY_COMMAND.clone());
         command_handler.register_command(commands::fun::penis::PENIS_COMMAND.clone());
+        command_handler.register_command(commands::admin::prefix::PREFIX_COMMAND.clone());
+        command_handler.register_command(commands::admin::toggle::DISABLE_COMMAND.clone());
+        command_handler.register_command(commands::admin::toggle::ENABLE_COMMAND.clone());
+        command_handler.register_command(commands::stats::STATS_COMMAND.clone());
+        command_handler.register_command(commands::stats::GLOBAL_STATS_COMMAND.clone());
+        command_handler.register_command(commands::image_gen::effect::DEEPFRY_COMMAND.clone());
+        command_handler.register_command(commands::image_gen::effect::DANK_COMMAND.clone());
+        command_handler.register_command(commands::image_gen::reload::RELOAD_TEMPLATES_COMMAND.clone());
 
-        commands::image_gen::command_gen::register_images(&mut command_handler, images.as_ref());
+        commands::image_gen::command_gen::register_images(&mut command_handler, &images.read());
 
         for command in command_handler.get_all_commands().iter() {
             info!("Registered command: {}", command.key);
@@ -158,21 +199,52 @@ fn main() {
     let command_handler = Arc::new(RwLock::new(command_handler));
     let safe = Arc::new(RwLock::new(Safe::new()));
     let eventwaiter = Arc::new(util::eventwaiter::Eventwaiter::new());
-    let handler = Handler::new(Arc::clone(&command_handler), Arc::clone(&safe), images, Arc::clone(&eventwaiter));
+    let guild_settings = Arc::new(GuildSettingsManager::new());
+    let cooldowns = Arc::new(CooldownManager::new());
+    // SERENITY ONLY ADDS THE "Bot " PREFIX FOR ITS OWN CLIENT
+    let bot_token = if discord_token.starts_with("Bot ") { discord_token.clone() } else { format!("Bot {}", discord_token) };
+    let application = fetch_application_info(&bot_token);
+    if let Some(ref application) = application {
+        register_interactions(&bot_token, application, &command_handler.read());
+    }
+    let owners = application.map(|a| vec![a.owner.id]).unwrap_or_default();
+    let handler = Handler::new(Arc::clone(&command_handler), Arc::clone(&safe), images, Arc::clone(&eventwaiter), guild_settings, Arc::clone(&cooldowns), owners);
+    let analytics = Arc::clone(&handler.analytics);
 
     // START CLIENT
     info!("Starting client");
     let mut client = Client::new(&discord_token, handler).expect("Could not create Client");
 
-    let scheduler = Scheduler::new(Arc::clone(&command_handler), Arc::clone(&safe), Arc::clone(&client.cache_and_http), Arc::clone(&eventwaiter));
+    let scheduler = Scheduler::new(Arc::clone(&command_handler), Arc::clone(&safe), Arc::clone(&client.cache_and_http), Arc::clone(&eventwaiter), Arc::clone(&cooldowns), analytics);
     start_scheduler(&scheduler);
 
     client.start_shards(2).expect("Could not start discord client");
 }
 
+fn fetch_application_info(token: &str) -> Option<CurrentApplicationInfo> {
+    match serenity::http::Http::new_with_token(token).get_current_application_info() {
+        Ok(k) => Some(k),
+        Err(e) => {
+            warn!("Could not fetch application info! Owner-only commands and slash commands will be unavailable: {}", e);
+            None
+        }
+    }
+}
+
+/// Registers every command as slash command. Set INTERACTIONS_GUILD to only register them on one guild
+pub(crate) fn register_interactions(token: &str, application: &CurrentApplicationInfo, command_manager: &CommandManager) {
+    let guild = std::env::var("INTERACTIONS_GUILD").ok().and_then(|g| g.parse::<u64>().ok()).map(GuildId);
+    let commands = interactions::commands::build_commands(command_manager.get_all_commands());
+    match interactions::commands::register(application.id.0, token, guild, &commands) {
+        Ok(()) => info!("Registered {} slash commands", commands.len()),
+        Err(e) => error!("Could not register slash commands: {}", e)
+    }
+}
+
 fn start_scheduler(scheduler: &Scheduler) {
     scheduler.clear_all();
     scheduler.schedule_repeated(1, schedules::clean_waiter); // EVERY 1 SECOND
+    scheduler.schedule_repeated(5 * 60, schedules::clean_cooldowns); // EVERY 5 MINUTES
     scheduler.schedule_repeated(1 * 60 * 30, schedules::update_statistics); // EVERY 30 MINUTES
     scheduler.schedule_repeated(1200, schedules::fetch_memes); // EVERY 20 MINUTES
     scheduler.schedule_repeated(24 * 60 * 60, schedules::fetch_dogs); // EVERY 24 HOURS
@@ -180,4 +252,6 @@ fn start_scheduler(scheduler: &Scheduler) {
     scheduler.schedule_repeated(24 * 60 * 60, schedules::fetch_rabbits); // EVERY 24 HOURS
     scheduler.schedule_repeated(12 * 60 * 60, schedules::fetch_aww); // EVERY 12 HOURS
     scheduler.schedule_repeated(1 * 60 * 60, schedules::update_topgg); // EVERY 1 HOUR
+    scheduler.schedule_repeated(30, schedules::flush_analytics); // EVERY 30 SECONDS
+    scheduler.schedule_repeated(60 * 60, schedules::roll_up_analytics); // EVERY 1 HOUR
 }
\ No newline at end of file
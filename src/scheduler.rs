@@ -4,7 +4,8 @@ use chrono::{DateTime, TimeZone, Utc};
 use serenity::CacheAndHttp;
 use serenity::prelude::RwLock;
 
-use crate::command_framework::CommandManager;
+use crate::command_framework::{CommandManager, CooldownManager};
+use crate::util::analytics::Analytics;
 use crate::util::eventwaiter::Eventwaiter;
 use crate::util::safe::Safe;
 
@@ -17,6 +18,8 @@ pub struct ScheduleArguments {
     pub scheduler: Arc<Scheduler>,
     pub serenity: Arc<CacheAndHttp>,
     pub event_waiter: Arc<Eventwaiter>,
+    pub cooldowns: Arc<CooldownManager>,
+    pub analytics: Arc<Analytics>,
 }
 
 pub struct Scheduler {
@@ -32,12 +35,12 @@ struct Schedule {
 }
 
 impl Scheduler {
-    pub fn new(cmd_handler: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, serenity: Arc<CacheAndHttp>, event_waiter: Arc<Eventwaiter>) -> ArcScheduler {
+    pub fn new(cmd_handler: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, serenity: Arc<CacheAndHttp>, event_waiter: Arc<Eventwaiter>, cooldowns: Arc<CooldownManager>, analytics: Arc<Analytics>) -> ArcScheduler {
         let s = Arc::new(Scheduler {
             schedules: Arc::new(RwLock::new(Vec::new())),
         });
 
-        s.start_schedule(cmd_handler, safe, Arc::clone(&s), Arc::clone(&serenity), Arc::clone(&event_waiter));
+        s.start_schedule(cmd_handler, safe, Arc::clone(&serenity), Arc::clone(&event_waiter), Arc::clone(&cooldowns), analytics);
         s
     }
 
@@ -72,9 +75,10 @@ impl Scheduler {
         schedules.shrink_to_fit();
     }
 
-    fn start_schedule(&self, command_manager: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, scheduler: Arc<Scheduler>, serenity: Arc<CacheAndHttp>, event_waiter: Arc<Eventwaiter>) {
+    fn start_schedule(self: &Arc<Self>, command_manager: Arc<RwLock<CommandManager>>, safe: Arc<RwLock<Safe>>, serenity: Arc<CacheAndHttp>, event_waiter: Arc<Eventwaiter>, cooldowns: Arc<CooldownManager>, analytics: Arc<Analytics>) {
         let schedules = Arc::clone(&self.schedules);
         let cmd_manager = Arc::clone(&command_manager);
+        let scheduler = Arc::clone(self);
 
         std::thread::spawn(move || {
             let schedules = schedules;
@@ -98,6 +102,8 @@ impl Scheduler {
                         let tm_scheduler = Arc::clone(&scheduler);
                         let tm_serenity = Arc::clone(&serenity);
                         let tm_event_waiter = Arc::clone(&event_waiter);
+                        let tm_cooldowns = Arc::clone(&cooldowns);
+                        let tm_analytics = Arc::clone(&analytics);
 
                         let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                             (schedule.function)(ScheduleArguments {
@@ -106,6 +112,8 @@ impl Scheduler {
                                 scheduler: tm_scheduler,
                                 serenity: tm_serenity,
                                 event_waiter: tm_event_waiter,
+                                cooldowns: tm_cooldowns,
+                                analytics: tm_analytics,
                             });
                         })
                         );
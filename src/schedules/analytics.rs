@@ -0,0 +1,15 @@
+use crate::scheduler::ScheduleArguments;
+use crate::util::analytics;
+
+pub fn flush_analytics(args: ScheduleArguments) {
+    if let Err(e) = args.analytics.flush() {
+        error!("ANALYTICS SCHEDULER: could not write usage records: {}", e);
+    }
+}
+
+pub fn roll_up_analytics(_args: ScheduleArguments) {
+    match analytics::roll_up() {
+        Ok(n) => info!("ANALYTICS SCHEDULER: Successfully rolled up {} daily aggregates!", n),
+        Err(e) => error!("ANALYTICS SCHEDULER: could not roll up daily aggregates: {}", e)
+    }
+}
\ No newline at end of file
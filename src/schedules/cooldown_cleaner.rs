@@ -0,0 +1,5 @@
+use crate::scheduler::ScheduleArguments;
+
+pub fn clean_cooldowns(args: ScheduleArguments) {
+    args.cooldowns.clean();
+}
\ No newline at end of file
@@ -1,5 +1,7 @@
+pub use analytics::{flush_analytics, roll_up_analytics};
 pub use aww::fetch_aww;
 pub use birb::fetch_birbs;
+pub use cooldown_cleaner::clean_cooldowns;
 pub use dog::fetch_dogs;
 pub use event_waiter_cleaner::clean_waiter;
 pub use meme::fetch_memes;
@@ -14,4 +16,6 @@ mod rabbit;
 mod aww;
 mod statistics;
 mod topgg_update;
-mod event_waiter_cleaner;
\ No newline at end of file
+mod event_waiter_cleaner;
+mod cooldown_cleaner;
+mod analytics;
\ No newline at end of file
@@ -0,0 +1,397 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error;
+use std::time::Duration;
+
+use bson::{Bson, doc, Document, UtcDateTime};
+use chrono::{DateTime, Utc};
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+use serenity::prelude::Mutex;
+use sha2::{Digest, Sha256};
+
+use crate::command_framework::{Command, CommandAction, CommandResult, ErrorKind};
+
+const COLLECTION: &str = "command_usage";
+const DAILY_COLLECTION: &str = "command_usage_daily";
+// RAW RECORDS ARE DELETED ONCE THEY ARE OLDER THAN THIS. THE DAILY AGGREGATES ARE KEPT
+const RAW_RETENTION_DAYS: i64 = 7;
+// IF THE DATABASE IS DOWN FOR A WHILE WE DROP RECORDS INSTEAD OF RUNNING OUT OF MEMORY
+const MAX_BUFFERED: usize = 10_000;
+
+/// How a dispatched command went
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+    PrintUsage,
+    OnCooldown,
+    TimedOut,
+    // A requirement of the command was not met
+    Denied,
+    InvalidArguments,
+    Error(ErrorKind),
+}
+
+impl Outcome {
+    pub fn of(result: &CommandResult) -> Outcome {
+        match result {
+            Ok(CommandAction::MarkAsSucceeded) => Outcome::Succeeded,
+            Ok(CommandAction::MarkAsFailed) => Outcome::Failed,
+            Ok(CommandAction::PrintUsage) => Outcome::PrintUsage,
+            Ok(CommandAction::OnCooldown(_)) => Outcome::OnCooldown,
+            Ok(CommandAction::TimedOut) => Outcome::TimedOut,
+            Err(e) => Outcome::Error(e.kind()),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match *self {
+            Outcome::Succeeded => "succeeded".to_owned(),
+            Outcome::Failed => "failed".to_owned(),
+            Outcome::PrintUsage => "print usage".to_owned(),
+            Outcome::OnCooldown => "on cooldown".to_owned(),
+            Outcome::TimedOut => "timed out".to_owned(),
+            Outcome::Denied => "denied".to_owned(),
+            Outcome::InvalidArguments => "invalid arguments".to_owned(),
+            Outcome::Error(kind) => format!("error: {}", kind.name()),
+        }
+    }
+
+    /// Whether the command failed on our side. Mistakes of the user do not count
+    pub fn is_failure(&self) -> bool {
+        match *self {
+            Outcome::Failed | Outcome::TimedOut => true,
+            Outcome::Error(kind) => kind != ErrorKind::BadInput,
+            _ => false
+        }
+    }
+}
+
+/// One dispatched command. Stored in the "command_usage" collection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub command: String,
+    pub guild_id: Option<i64>,
+    // SALTED HASH OF THE USER ID. WE ONLY NEED TO TELL USERS APART
+    pub user_hash: String,
+    // NONE IF THE COMMAND HAS NOT BEEN EXECUTED
+    pub latency_ms: Option<i64>,
+    pub outcome: String,
+    pub failed: bool,
+    pub time: UtcDateTime,
+}
+
+/// Usage of one command summed up over a time window
+#[derive(Clone, Debug, Default)]
+pub struct CommandUsage {
+    pub command: String,
+    pub uses: i64,
+    pub failures: i64,
+    latency_sum: i64,
+    latency_count: i64,
+}
+
+impl CommandUsage {
+    pub fn failure_rate(&self) -> f64 {
+        if self.uses == 0 { 0.0 } else { self.failures as f64 / self.uses as f64 }
+    }
+
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.latency_count == 0 {
+            return None;
+        }
+        Some(Duration::from_millis((self.latency_sum / self.latency_count) as u64))
+    }
+
+    fn add(&mut self, doc: &Document) {
+        self.uses += number(doc, "uses");
+        self.failures += number(doc, "failures");
+        self.latency_sum += number(doc, "latency_sum");
+        self.latency_count += number(doc, "latency_count");
+    }
+}
+
+/// Buffers the usage records in memory. The "flush_analytics" schedule writes them to the database
+pub struct Analytics {
+    buffer: Mutex<VecDeque<UsageRecord>>,
+    salt: String,
+}
+
+impl Analytics {
+    /// The user ids are hashed with ANALYTICS_SALT. If it is not set a random salt is used,
+    /// so users cannot be told apart across restarts
+    pub fn new() -> Self {
+        let salt = match std::env::var("ANALYTICS_SALT") {
+            Ok(s) => s,
+            Err(_) => {
+                warn!("ANALYTICS: ANALYTICS_SALT is not set. Using a random salt");
+                format!("{:016x}", rand::random::<u64>())
+            }
+        };
+        Analytics {
+            buffer: Mutex::new(VecDeque::new()),
+            salt,
+        }
+    }
+
+    pub fn record(&self, cmd: &Command, msg: &Message, outcome: Outcome, latency: Option<Duration>) {
+        let record = UsageRecord {
+            command: cmd.key.to_owned(),
+            guild_id: msg.guild_id.map(|g| g.0 as i64),
+            user_hash: self.hash_user(msg.author.id.0),
+            latency_ms: latency.map(|l| l.as_millis() as i64),
+            outcome: outcome.name(),
+            failed: outcome.is_failure(),
+            time: UtcDateTime(Utc::now()),
+        };
+        self.push(record);
+    }
+
+    /// Drops the oldest record if the buffer is full
+    fn push(&self, record: UsageRecord) {
+        let mut buffer = self.buffer.lock();
+        if buffer.len() >= MAX_BUFFERED {
+            buffer.pop_front();
+        }
+        buffer.push_back(record);
+    }
+
+    /// Writes all buffered records to the database. They are buffered again if the database is not reachable
+    pub fn flush(&self) -> Result<usize, Box<dyn error::Error>> {
+        let records: VecDeque<UsageRecord> = std::mem::take(&mut *self.buffer.lock());
+        if records.is_empty() {
+            return Ok(0);
+        }
+
+        let mut docs = Vec::with_capacity(records.len());
+        for r in records.iter() {
+            match bson::to_bson(r)? {
+                Bson::Document(d) => docs.push(d),
+                _ => return Err("usage record did not serialize to a document".into())
+            }
+        }
+        if let Err(e) = crate::DB.collection(COLLECTION).insert_many(docs, None) {
+            let mut buffer = self.buffer.lock();
+            let keep = MAX_BUFFERED.saturating_sub(buffer.len());
+            let skip = records.len().saturating_sub(keep);
+            // THE OLDER RECORDS GO BEFORE THE ONES RECORDED IN THE MEANTIME
+            for r in records.into_iter().skip(skip).rev() {
+                buffer.push_front(r);
+            }
+            return Err(e.into());
+        }
+        Ok(records.len())
+    }
+
+    fn hash_user(&self, user_id: u64) -> String {
+        let mut hasher = Sha256::new();
+        hasher.input(self.salt.as_bytes());
+        hasher.input(user_id.to_string().as_bytes());
+        hasher.result().iter().take(12).map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Sums the usage of the last `days` days (today included) up, on one guild or on all guilds if `guild_id` is None.
+/// Rolled up days are read from the daily aggregates, all other days (e.g. today and yesterday until the next roll up)
+/// from the raw records. Sorted by uses, the most used command comes first
+pub fn usage(days: i64, guild_id: Option<GuildId>) -> Result<Vec<CommandUsage>, Box<dyn error::Error>> {
+    let today = today();
+    let start = today - chrono::Duration::days(days - 1);
+
+    let mut filter = doc! {"day": {"$gte": Bson::UtcDatetime(start), "$lte": Bson::UtcDatetime(today)}};
+    if let Some(g) = guild_id {
+        filter.insert("guild_id", g.0 as i64);
+    }
+    let daily = crate::DB.collection(DAILY_COLLECTION).find(Some(filter), None)?.collect::<Result<Vec<Document>, _>>()?;
+    // A GUILD WITHOUT USAGE ON A DAY HAS NO AGGREGATES FOR IT. ITS RAW RECORDS ARE EMPTY, TOO
+    let rolled_up: HashSet<DateTime<Utc>> = daily.iter().filter_map(|d| d.get_utc_datetime("day").ok()).cloned().collect();
+
+    let mut raw = Vec::new();
+    for day in raw_days(start, days, today, &rolled_up) {
+        raw.append(&mut aggregate_raw(day, day + chrono::Duration::days(1), guild_id)?);
+    }
+    Ok(merge(daily.iter().chain(raw.iter()))?)
+}
+
+/// The days of the window which have not been rolled up and still have raw records
+fn raw_days(start: DateTime<Utc>, days: i64, today: DateTime<Utc>, rolled_up: &HashSet<DateTime<Utc>>) -> Vec<DateTime<Utc>> {
+    // OLDER RAW RECORDS ARE DELETED BY THE ROLL UP
+    let oldest_raw = today - chrono::Duration::days(RAW_RETENTION_DAYS);
+    (0..days)
+        .map(|i| start + chrono::Duration::days(i))
+        .filter(|day| *day >= oldest_raw && !rolled_up.contains(day))
+        .collect()
+}
+
+/// Sums daily aggregates and raw aggregations up by command
+fn merge<'a, I: Iterator<Item=&'a Document>>(docs: I) -> Result<Vec<CommandUsage>, bson::ordered::ValueAccessError> {
+    let mut usages: HashMap<String, CommandUsage> = HashMap::new();
+    for doc in docs {
+        let command = doc.get_str("command")?;
+        usages.entry(command.to_owned())
+            .or_insert_with(|| CommandUsage { command: command.to_owned(), ..Default::default() })
+            .add(doc);
+    }
+
+    let mut usages: Vec<CommandUsage> = usages.into_values().collect();
+    usages.sort_by(|a, b| b.uses.cmp(&a.uses).then_with(|| a.command.cmp(&b.command)));
+    Ok(usages)
+}
+
+/// Aggregates the raw records of every completed day which is still retained into the "command_usage_daily" collection
+/// (one document per day, command and guild) and deletes older raw records afterwards.
+/// Running it more than once a day is fine, the aggregates of a day are replaced
+pub fn roll_up() -> Result<usize, Box<dyn error::Error>> {
+    let today = today();
+    let daily = crate::DB.collection(DAILY_COLLECTION);
+    let mut written = 0;
+
+    for i in 1..=RAW_RETENTION_DAYS {
+        let day = today - chrono::Duration::days(i);
+        let docs: Vec<Document> = aggregate_raw(day, day + chrono::Duration::days(1), None)?.into_iter()
+            .map(|d| doc! {
+                "day": Bson::UtcDatetime(day),
+                "command": d.get("command").cloned().unwrap_or(Bson::Null),
+                "guild_id": d.get("guild_id").cloned().unwrap_or(Bson::Null),
+                "uses": number(&d, "uses"),
+                "failures": number(&d, "failures"),
+                "latency_sum": number(&d, "latency_sum"),
+                "latency_count": number(&d, "latency_count"),
+            })
+            .collect();
+        // WHILE THE AGGREGATES OF THE DAY ARE MISSING, USAGE READS THE RAW RECORDS INSTEAD
+        daily.delete_many(doc! {"day": Bson::UtcDatetime(day)}, None)?;
+        if !docs.is_empty() {
+            written += docs.len();
+            daily.insert_many(docs, None)?;
+        }
+    }
+
+    let oldest = today - chrono::Duration::days(RAW_RETENTION_DAYS);
+    crate::DB.collection(COLLECTION).delete_many(doc! {"time": {"$lt": Bson::UtcDatetime(oldest)}}, None)?;
+    Ok(written)
+}
+
+/// Groups the raw records between `from` (inclusive) and `to` (exclusive) by command and guild.
+/// The documents have the same fields as the daily aggregates
+fn aggregate_raw(from: DateTime<Utc>, to: DateTime<Utc>, guild_id: Option<GuildId>) -> Result<Vec<Document>, Box<dyn error::Error>> {
+    let mut filter = doc! {"time": {"$gte": Bson::UtcDatetime(from), "$lt": Bson::UtcDatetime(to)}};
+    if let Some(g) = guild_id {
+        filter.insert("guild_id", g.0 as i64);
+    }
+    let cursor = crate::DB.collection(COLLECTION).aggregate(vec![
+        doc! {"$match": filter},
+        doc! {"$group": {
+            "_id": {"command": "$command", "guild_id": "$guild_id"},
+            "uses": {"$sum": 1},
+            "failures": {"$sum": {"$cond": ["$failed", 1, 0]}},
+            "latency_sum": {"$sum": {"$ifNull": ["$latency_ms", 0]}},
+            // NULL IF THE COMMAND HAS NOT BEEN EXECUTED
+            "latency_count": {"$sum": {"$cond": [{"$gt": ["$latency_ms", Bson::Null]}, 1, 0]}},
+        }},
+        doc! {"$project": {
+            "_id": 0,
+            "command": "$_id.command",
+            "guild_id": "$_id.guild_id",
+            "uses": 1,
+            "failures": 1,
+            "latency_sum": 1,
+            "latency_count": 1,
+        }},
+    ], None)?;
+    Ok(cursor.collect::<Result<Vec<Document>, _>>()?)
+}
+
+/// Midnight (UTC) of the current day
+fn today() -> DateTime<Utc> {
+    Utc::today().and_hms(0, 0, 0)
+}
+
+/// $sum returns 32 or 64 bit integers depending on the size of the result
+fn number(doc: &Document, key: &str) -> i64 {
+    match doc.get(key) {
+        Some(Bson::I32(i)) => *i as i64,
+        Some(Bson::I64(i)) => *i,
+        Some(Bson::FloatingPoint(f)) => *f as i64,
+        _ => 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record(command: &str) -> UsageRecord {
+        UsageRecord {
+            command: command.to_owned(),
+            guild_id: None,
+            user_hash: String::new(),
+            latency_ms: None,
+            outcome: Outcome::Succeeded.name(),
+            failed: false,
+            time: UtcDateTime(Utc::now()),
+        }
+    }
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.ymd(2020, 12, d).and_hms(0, 0, 0)
+    }
+
+    #[test]
+    fn failures() {
+        assert!(Outcome::Failed.is_failure());
+        assert!(Outcome::TimedOut.is_failure());
+        assert!(Outcome::Error(ErrorKind::Internal).is_failure());
+        assert!(Outcome::Error(ErrorKind::UpstreamUnavailable).is_failure());
+        // MISTAKES OF THE USER
+        assert!(!Outcome::Error(ErrorKind::BadInput).is_failure());
+        assert!(!Outcome::InvalidArguments.is_failure());
+        assert!(!Outcome::OnCooldown.is_failure());
+        assert!(!Outcome::Succeeded.is_failure());
+    }
+
+    #[test]
+    fn buffer_drops_oldest_records() {
+        let analytics = Analytics { buffer: Mutex::new(VecDeque::new()), salt: String::new() };
+        for i in 0..MAX_BUFFERED + 2 {
+            analytics.push(record(&i.to_string()));
+        }
+        let buffer = analytics.buffer.lock();
+        assert_eq!(buffer.len(), MAX_BUFFERED);
+        assert_eq!(buffer.front().unwrap().command, "2");
+        assert_eq!(buffer.back().unwrap().command, (MAX_BUFFERED + 1).to_string());
+    }
+
+    #[test]
+    fn raw_days_skip_rolled_up_and_deleted_days() {
+        let today = day(20);
+        let rolled_up: HashSet<DateTime<Utc>> = [day(17), day(18)].iter().cloned().collect();
+        // THE RAW RECORDS BEFORE THE 13TH ARE DELETED
+        assert_eq!(raw_days(day(10), 11, today, &rolled_up), vec![day(13), day(14), day(15), day(16), day(19), day(20)]);
+        assert_eq!(raw_days(today, 1, today, &rolled_up), vec![today]);
+    }
+
+    #[test]
+    fn merges_daily_and_raw_counts() {
+        let daily = [
+            doc! {"day": Bson::UtcDatetime(day(18)), "command": "meme", "uses": 10i64, "failures": 1i64, "latency_sum": 1000i64, "latency_count": 10i64},
+            doc! {"day": Bson::UtcDatetime(day(18)), "command": "love", "uses": 2i64, "failures": 0i64, "latency_sum": 0i64, "latency_count": 0i64},
+        ];
+        // $sum RETURNS 32 BIT INTEGERS FOR SMALL RESULTS
+        let raw = [
+            doc! {"command": "meme", "uses": 5i32, "failures": 4i32, "latency_sum": 2000i32, "latency_count": 5i32},
+            doc! {"command": "stats", "uses": 1i32, "failures": 0i32, "latency_sum": 0i32, "latency_count": 0i32},
+        ];
+
+        let usages = merge(daily.iter().chain(raw.iter())).unwrap();
+        let commands: Vec<&str> = usages.iter().map(|u| u.command.as_str()).collect();
+        assert_eq!(commands, vec!["meme", "love", "stats"]);
+        assert_eq!(usages[0].uses, 15);
+        assert_eq!(usages[0].failures, 5);
+        assert!((usages[0].failure_rate() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(usages[0].average_latency(), Some(Duration::from_millis(200)));
+        assert_eq!(usages[1].average_latency(), None);
+    }
+}
\ No newline at end of file
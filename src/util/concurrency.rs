@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Limits how many threads may do something at the same time. Never blocks
+pub struct ConcurrencyLimit {
+    max: usize,
+    current: AtomicUsize,
+}
+
+/// Frees its slot when dropped
+pub struct ConcurrencyPermit<'a> {
+    limit: &'a ConcurrencyLimit
+}
+
+impl ConcurrencyLimit {
+    pub const fn new(max: usize) -> Self {
+        ConcurrencyLimit {
+            max,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns None if all slots are taken
+    pub fn try_acquire(&self) -> Option<ConcurrencyPermit<'_>> {
+        let mut current = self.current.load(Ordering::SeqCst);
+        loop {
+            if current >= self.max {
+                return None;
+            }
+            match self.current.compare_exchange(current, current + 1, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return Some(ConcurrencyPermit { limit: self }),
+                Err(actual) => current = actual
+            }
+        }
+    }
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        self.limit.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
\ No newline at end of file
@@ -7,7 +7,6 @@ use serenity::model::user::User;
 use crate::command_framework::{CommandArguments, CommandManager};
 use crate::command_framework::prelude::{Context, RwLock};
 use crate::scheduler::ScheduleArguments;
-use crate::StaticSettings;
 
 #[derive(Clone)]
 #[allow(dead_code)]
@@ -32,7 +31,7 @@ pub struct ReactionEvent {
     pub message: Message,
     pub author_message: Message,
     pub handler: Arc<RwLock<CommandManager>>,
-    pub settings: Arc<StaticSettings>,
+    pub prefix: String,
     pub callback: fn(&Context, &mut ReactionEvent, &Reaction) -> EventAction,
 }
 
@@ -46,7 +45,7 @@ impl ReactionEvent {
             message: message.clone(),
             author_message: args.m.clone(),
             handler: Arc::clone(&args.handler),
-            settings: Arc::clone(&args.settings),
+            prefix: args.prefix.clone(),
             callback,
         }
     }
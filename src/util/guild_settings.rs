@@ -0,0 +1,163 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bson::{Bson, doc};
+use mongodb::options::{FindOneOptions, ReplaceOptions};
+use serenity::model::id::GuildId;
+use serenity::prelude::{Mutex, RwLock};
+use threadpool::ThreadPool;
+
+use crate::command_framework::Command;
+use crate::commands::category::Category;
+
+const COLLECTION: &str = "guild_settings";
+// AFTER A FAILED LOOKUP THE DATABASE IS NOT ASKED AGAIN FOR THIS LONG
+const FAILURE_BACKOFF: Duration = Duration::from_secs(30);
+// A LOOKUP WHICH TAKES LONGER IS ABORTED BY THE DATABASE
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const LOADER_THREADS: usize = 2;
+
+/// Per guild settings. Stored in the "guild_settings" collection with the guild id as _id
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GuildSettings {
+    #[serde(rename = "_id")]
+    pub guild_id: i64,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    // COMMAND KEYS
+    #[serde(default)]
+    pub disabled_commands: Vec<String>,
+    // CATEGORY NAMES
+    #[serde(default)]
+    pub disabled_categories: Vec<String>,
+}
+
+impl GuildSettings {
+    fn new(guild_id: GuildId) -> Self {
+        GuildSettings {
+            guild_id: guild_id.0 as i64,
+            prefix: None,
+            disabled_commands: vec![],
+            disabled_categories: vec![],
+        }
+    }
+
+    /// Returns the guild's prefix or the given default prefix if the guild has not set one
+    pub fn prefix_or<'a>(&'a self, default: &'a str) -> &'a str {
+        match self.prefix {
+            Some(ref s) => s,
+            None => default
+        }
+    }
+
+    /// Whether the command or its category has been disabled on this guild. Admin commands cannot be disabled
+    pub fn is_disabled(&self, cmd: &Command) -> bool {
+        if cmd.category == Category::Admin {
+            return false;
+        }
+        self.disabled_commands.iter().any(|k| k == cmd.key)
+            || self.disabled_categories.iter().any(|c| *c == cmd.category.to_string())
+    }
+}
+
+/// Caches the guild settings so we only have to ask the database once per guild.
+/// The settings are loaded on own threads, the shard threads never wait for the database
+pub struct GuildSettingsManager {
+    cache: RwLock<HashMap<GuildId, GuildSettings>>,
+    // GUILDS WHOSE SETTINGS ARE BEING LOADED RIGHT NOW
+    loading: Mutex<HashSet<GuildId>>,
+    pool: Mutex<ThreadPool>,
+    // THE LAST TIME THE DATABASE COULD NOT BE REACHED
+    failed_at: Mutex<Option<Instant>>,
+}
+
+impl GuildSettingsManager {
+    pub fn new() -> Self {
+        GuildSettingsManager {
+            cache: RwLock::new(HashMap::new()),
+            loading: Mutex::new(HashSet::new()),
+            pool: Mutex::new(ThreadPool::with_name("guild settings loader".to_owned(), LOADER_THREADS)),
+            failed_at: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached settings of the guild. If they are not cached yet, they are loaded in the background
+    /// and the default settings are returned in the meantime.
+    /// If the database is not reachable it is not asked again until FAILURE_BACKOFF has passed
+    pub fn get(self: &Arc<Self>, guild_id: GuildId) -> GuildSettings {
+        if let Some(s) = self.cache.read().get(&guild_id) {
+            return s.clone();
+        }
+        self.preload(guild_id);
+        GuildSettings::new(guild_id)
+    }
+
+    /// Loads the settings of the guild in the background unless they are cached or loading already
+    pub fn preload(self: &Arc<Self>, guild_id: GuildId) {
+        if self.cache.read().contains_key(&guild_id) || self.backing_off() || !self.loading.lock().insert(guild_id) {
+            return;
+        }
+
+        let manager = Arc::clone(self);
+        self.pool.lock().execute(move || {
+            match Self::load(guild_id) {
+                // UPDATE MAY HAVE CACHED NEWER SETTINGS IN THE MEANTIME
+                Ok(k) => {
+                    manager.cache.write().entry(guild_id).or_insert_with(|| k.unwrap_or_else(|| GuildSettings::new(guild_id)));
+                }
+                Err(e) => {
+                    error!("GUILD SETTINGS: could not load settings of guild {}: {}. Using the defaults for {}s", guild_id.0, e, FAILURE_BACKOFF.as_secs());
+                    *manager.failed_at.lock() = Some(Instant::now());
+                }
+            }
+            manager.loading.lock().remove(&guild_id);
+        });
+    }
+
+    /// Modifies the settings of the guild and writes them back to the database
+    pub fn update<F>(&self, guild_id: GuildId, f: F) -> Result<GuildSettings, Box<dyn std::error::Error>> where F: FnOnce(&mut GuildSettings) {
+        let cached = self.cache.read().get(&guild_id).cloned();
+        // NOT WITH GET. ITS DEFAULTS WOULD OVERWRITE THE STORED SETTINGS IF THE DATABASE IS NOT REACHABLE
+        let mut settings = match cached {
+            Some(s) => s,
+            None => Self::load(guild_id)?.unwrap_or_else(|| GuildSettings::new(guild_id))
+        };
+        f(&mut settings);
+
+        let doc = match bson::to_bson(&settings)? {
+            Bson::Document(d) => d,
+            _ => return Err("guild settings did not serialize to a document".into())
+        };
+        crate::DB.collection(COLLECTION).replace_one(doc! {"_id": settings.guild_id}, doc, ReplaceOptions::builder().upsert(Some(true)).build())?;
+
+        self.cache.write().insert(guild_id, settings.clone());
+        Ok(settings)
+    }
+
+    /// Removes the guild from the cache. It will be reloaded from the database the next time it is needed
+    pub fn invalidate(&self, guild_id: GuildId) {
+        self.cache.write().remove(&guild_id);
+    }
+
+    fn backing_off(&self) -> bool {
+        let mut failed_at = self.failed_at.lock();
+        match *failed_at {
+            Some(t) if t.elapsed() < FAILURE_BACKOFF => true,
+            Some(_) => {
+                *failed_at = None;
+                false
+            }
+            None => false
+        }
+    }
+
+    fn load(guild_id: GuildId) -> Result<Option<GuildSettings>, Box<dyn std::error::Error>> {
+        let options = FindOneOptions::builder().max_time(Some(QUERY_TIMEOUT)).build();
+        let doc = crate::DB.collection(COLLECTION).find_one(doc! {"_id": guild_id.0 as i64}, options)?;
+        match doc {
+            Some(d) => Ok(Some(bson::from_bson(Bson::Document(d))?)),
+            None => Ok(None)
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{error, fmt, io};
+use std::fmt::Formatter;
+use std::io::Cursor;
+
+use gif::SetParameter;
+use image::{AnimationDecoder, DynamicImage, ImageFormat, ImageResult, RgbaImage};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::util::image::Dimension;
+
+pub const MAX_FRAMES: u32 = 50;
+// MOST CLIENTS PLAY FASTER GIFS SLOWER
+pub const MIN_DELAY: u32 = 20;
+// BROWSERS PLAY GIF FRAMES WITHOUT DELAY WITH THIS DELAY
+const DEFAULT_INPUT_DELAY: u32 = 100;
+// FRAMES * WIDTH * HEIGHT. KEEPS THE RENDER TIME AND THE MEMORY USAGE IN CHECK
+pub const MAX_PIXELS: u64 = 30_000_000;
+// DISCORD'S UPLOAD LIMIT IS 8MB. LEAVE SOME SPACE FOR THE REST OF THE REQUEST
+pub const MAX_OUTPUT_SIZE: usize = 7_500_000;
+// GIFS ARE NOT SCALED DOWN BELOW THIS WIDTH OR HEIGHT TO FIT INTO THE UPLOAD LIMIT
+pub const MIN_OUTPUT_SIDE: u32 = 64;
+// 1 IS THE BEST QUALITY AND THE SLOWEST, 30 THE WORST AND THE FASTEST
+const QUANTIZE_SPEED: i32 = 10;
+
+/// Turns a template into a looping GIF
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub frames: u32,
+    // MILLISECONDS BETWEEN TWO FRAMES
+    pub delay: u32,
+}
+
+impl Animation {
+    // MILLISECONDS
+    fn duration(&self) -> u32 {
+        self.frames * self.delay
+    }
+
+    /// Combines the template's own animation with the animations of its inputs. The result is as long as the longest of them
+    /// and has at most MAX_FRAMES frames. Longer animations get a longer delay instead of being cut off
+    pub fn combine(template: Option<&Animation>, inputs: &[Animation], width: u32, height: u32) -> Option<Animation> {
+        let delay = match template {
+            Some(a) => a.delay,
+            None => inputs.iter().map(|a| a.delay).min()?
+        };
+        let duration = template.into_iter().chain(inputs.iter()).map(|a| a.duration()).max()?;
+
+        let max_frames = (MAX_PIXELS / (width as u64 * height as u64).max(1)).max(1).min(MAX_FRAMES as u64) as u32;
+        let mut animation = Animation {
+            frames: ceil_div(duration, delay),
+            delay,
+        };
+        if animation.frames > max_frames {
+            animation.delay = ceil_div(duration, max_frames);
+            animation.frames = ceil_div(duration, animation.delay);
+        }
+        Some(animation)
+    }
+}
+
+fn ceil_div(a: u32, b: u32) -> u32 {
+    (a + b - 1) / b.max(1)
+}
+
+/// The frame which is currently rendered
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub index: u32,
+    pub count: u32,
+    // MILLISECONDS SINCE THE FIRST FRAME
+    pub time: u32,
+}
+
+impl Frame {
+    /// The only frame of templates without animation
+    pub const STILL: Frame = Frame { index: 0, count: 1, time: 0 };
+}
+
+/// An image which may have multiple frames (e.g. an animated avatar)
+#[derive(Clone)]
+pub struct ImageFrames {
+    frames: Vec<DynamicImage>,
+    // MILLISECONDS BETWEEN TWO FRAMES
+    delay: u32,
+}
+
+impl ImageFrames {
+    pub fn still(img: DynamicImage) -> Self {
+        ImageFrames {
+            frames: vec![img],
+            delay: 0,
+        }
+    }
+
+    /// Decodes every frame of GIFs (up to MAX_FRAMES or MAX_PIXELS). Other formats are decoded as still image
+    pub fn decode(buf: &[u8]) -> ImageResult<Self> {
+        if image::guess_format(buf)? != ImageFormat::GIF {
+            return Ok(Self::still(image::load_from_memory(buf)?));
+        }
+
+        let mut frames = Vec::new();
+        let mut pixels = 0u64;
+        let mut delay_sum = 0u32;
+        for frame in image::gif::Decoder::new(Cursor::new(buf))?.into_frames().take(MAX_FRAMES as usize) {
+            let frame = frame?;
+            pixels += frame.buffer().width() as u64 * frame.buffer().height() as u64;
+            if pixels > MAX_PIXELS && !frames.is_empty() {
+                break;
+            }
+            delay_sum += match frame.delay().to_integer() as u32 {
+                0 => DEFAULT_INPUT_DELAY,
+                d => d.max(MIN_DELAY)
+            };
+            frames.push(DynamicImage::ImageRgba8(frame.into_buffer()));
+        }
+
+        let delay = delay_sum / frames.len().max(1) as u32;
+        match frames.len() {
+            0 => Err(image::ImageError::ImageEnd),
+            1 => Ok(Self::still(frames.remove(0))),
+            _ => Ok(ImageFrames { frames, delay })
+        }
+    }
+
+    pub fn animation(&self) -> Option<Animation> {
+        if self.frames.len() <= 1 {
+            return None;
+        }
+        Some(Animation {
+            frames: self.frames.len() as u32,
+            delay: self.delay,
+        })
+    }
+
+    /// The frame which is shown at the time of the rendered frame. Starts over when the end is reached
+    pub fn at(&self, frame: &Frame) -> &DynamicImage {
+        if self.frames.len() <= 1 || self.delay == 0 {
+            return &self.frames[0];
+        }
+        &self.frames[(frame.time / self.delay) as usize % self.frames.len()]
+    }
+
+    pub fn map<F: Fn(&DynamicImage) -> DynamicImage>(&self, f: F) -> ImageFrames {
+        ImageFrames {
+            frames: self.frames.iter().map(f).collect(),
+            delay: self.delay,
+        }
+    }
+}
+
+/// Moves a feature around from frame to frame. Does nothing on templates without animation
+#[derive(Clone, Debug, Default)]
+pub struct FrameTransform {
+    // THE FEATURE IS MOVED BY UP TO THIS MANY PIXELS IN EVERY DIRECTION (X, Y)
+    pub jitter: [u32; 2],
+    // FIXED OFFSET PER FRAME. STARTS OVER IF THERE ARE MORE FRAMES THAN OFFSETS
+    pub offsets: Vec<[i32; 2]>,
+}
+
+impl FrameTransform {
+    /// The jitter is random but the same for every render of a frame, so the output does not change between runs
+    pub fn offset(&self, frame: &Frame, dimension: &Dimension) -> (i32, i32) {
+        if frame.count <= 1 {
+            return (0, 0);
+        }
+
+        let (mut x, mut y) = match self.offsets.get(frame.index as usize % self.offsets.len().max(1)) {
+            Some([x, y]) => (*x, *y),
+            None => (0, 0)
+        };
+        if self.jitter != [0, 0] {
+            let seed = (frame.index as u64) << 32 | (dimension.x as u64) << 16 | dimension.y as u64;
+            let mut rng = StdRng::seed_from_u64(seed);
+            x += rng.gen_range(-(self.jitter[0] as i32), self.jitter[0] as i32 + 1);
+            y += rng.gen_range(-(self.jitter[1] as i32), self.jitter[1] as i32 + 1);
+        }
+        (x, y)
+    }
+}
+
+/// The GIF does not fit into the upload limit, not even with fewer frames and scaled down
+#[derive(Debug)]
+pub struct OutputTooLarge;
+
+impl fmt::Display for OutputTooLarge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the GIF is larger than {}MB", MAX_OUTPUT_SIZE / 1_000_000)
+    }
+}
+
+impl error::Error for OutputTooLarge {}
+
+/// Encodes the frames as endlessly looping GIF. All frames need to have the same size
+pub fn encode_gif(frames: Vec<RgbaImage>, delay: u32) -> Result<Vec<u8>, io::Error> {
+    let mut buf = Vec::new();
+    let (width, height) = match frames.first() {
+        Some(f) => (f.width() as u16, f.height() as u16),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "no frames to encode"))
+    };
+
+    {
+        let mut encoder = gif::Encoder::new(&mut buf, width, height, &[])?;
+        encoder.set(gif::Repeat::Infinite)?;
+        for frame in frames {
+            let mut pixels = frame.into_raw();
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, QUANTIZE_SPEED);
+            // GIF DELAYS ARE IN HUNDREDTHS OF A SECOND
+            frame.delay = (delay / 10) as u16;
+            encoder.write_frame(&frame)?;
+        }
+    } // DROP ENCODER. IT WRITES THE TRAILER
+
+    Ok(buf)
+}
\ No newline at end of file
@@ -0,0 +1,297 @@
+use std::{error, fs};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::{DynamicImage, FilterType, GenericImageView, ImageOutputFormat, Pixel, RgbaImage};
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
+
+use crate::util::image::animation::{Frame, ImageFrames};
+
+// THE BUILT-IN PRESETS. THEIR STICKERS ARE READ FROM THE DIRECTORY OF THE SAME NAME IN THE TEMPLATES DIRECTORY
+const PRESETS: [(&str, &[&str]); 2] = [
+    ("deepfry", &["saturate:3", "contrast:60", "sharpen", "noise:24", "stickers:4:{}", "jpeg:8:3"]),
+    ("dank", &["saturate:2", "overlay:0.25:{}/red.png", "stickers:5:{}", "noise:12", "jpeg:20:2"]),
+];
+// STICKERS ARE SCALED TO THIS FRACTION OF THE SHORTER SIDE OF THE IMAGE
+const STICKER_MIN_SIZE: f32 = 0.2;
+const STICKER_MAX_SIZE: f32 = 0.45;
+const MAX_STICKERS: u32 = 32;
+const MAX_JPEG_PASSES: u32 = 10;
+
+/// A filter which is applied to a whole image. Effects are applied in order, so they can be combined
+#[derive(Clone, Debug)]
+pub enum Effect {
+    // 0 IS GRAY, 1 DOES NOTHING, EVERYTHING ABOVE MAKES THE COLORS MORE INTENSE
+    Saturate(f32),
+    // PERCENT. NEGATIVE VALUES REDUCE THE CONTRAST
+    Contrast(f32),
+    Brighten(i32),
+    Sharpen,
+    // QUALITY (1-100), NUMBER OF PASSES
+    Jpeg(u8, u32),
+    // MAXIMUM CHANGE PER CHANNEL
+    Noise(u8),
+    // SIZE OF A BLOCK IN PIXELS
+    Pixelate(u32),
+    // SIGMA
+    Blur(f32),
+    Invert,
+    // DEGREES
+    Hue(i32),
+    // STRETCHED OVER THE WHOLE IMAGE WITH THIS OPACITY (0-1)
+    Overlay(Arc<RgbaImage>, f32),
+    // SCATTERS THIS MANY RANDOMLY PICKED STICKERS
+    Stickers(Arc<Vec<RgbaImage>>, u32),
+}
+
+/// The presets (e.g. "deepfry") which can be used by commands and templates. Loaded once at startup
+pub struct EffectRegistry {
+    presets: HashMap<String, Vec<Effect>>,
+}
+
+impl EffectRegistry {
+    /// Presets whose assets are missing are left out
+    pub fn load(path: &Path) -> Self {
+        let mut presets = HashMap::new();
+        let empty = EffectRegistry { presets: HashMap::new() };
+        for (name, specs) in PRESETS.iter() {
+            let dir = path.join(name);
+            let specs: Vec<String> = specs.iter().map(|s| s.replace("{}", &dir.to_string_lossy())).collect();
+            match empty.parse(&specs) {
+                Ok(effects) => { presets.insert(name.to_string(), effects); }
+                Err(e) => warn!(r#"EFFECTS: preset "{}" is unavailable: {}"#, name, e)
+            }
+        }
+        EffectRegistry { presets }
+    }
+
+    pub fn preset(&self, name: &str) -> Option<&[Effect]> {
+        self.presets.get(name).map(|p| p.as_slice())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.presets.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Parses effects like "blur:2" or "jpeg:10:3". The name of a preset stands for all of its effects.
+    /// Images are read right away, so broken paths are noticed at startup
+    pub fn parse(&self, specs: &[String]) -> Result<Vec<Effect>, String> {
+        let mut effects = Vec::new();
+        for spec in specs {
+            if let Some(preset) = self.presets.get(spec.as_str()) {
+                effects.extend(preset.iter().cloned());
+                continue;
+            }
+            // PATHS ARE ALWAYS THE LAST ARGUMENT, SO THEY MAY CONTAIN COLONS
+            let mut parts = spec.splitn(3, ':');
+            let name = parts.next().unwrap_or_default();
+            let (first, second) = (parts.next(), parts.next());
+            let effect = match name {
+                "saturate" => Effect::Saturate(number(first, name)?),
+                "contrast" => Effect::Contrast(number(first, name)?),
+                "brighten" => Effect::Brighten(number(first, name)?),
+                "sharpen" => Effect::Sharpen,
+                "jpeg" => {
+                    let quality: u8 = number(first, name)?;
+                    let passes = match second {
+                        Some(_) => number(second, name)?,
+                        None => 1
+                    };
+                    Effect::Jpeg(quality.clamp(1, 100), passes.clamp(1, MAX_JPEG_PASSES))
+                }
+                "noise" => Effect::Noise(number(first, name)?),
+                "pixelate" => Effect::Pixelate(number::<u32>(first, name)?.max(1)),
+                "blur" => Effect::Blur(number(first, name)?),
+                "invert" => Effect::Invert,
+                "hue" => Effect::Hue(number(first, name)?),
+                "overlay" => {
+                    let path = second.ok_or(r#"effect "overlay" needs an opacity and a path"#)?;
+                    let img = open(Path::new(path)).map_err(|e| format!(r#"could not read overlay image "{}": {}"#, path, e))?;
+                    Effect::Overlay(Arc::new(img.to_rgba()), number::<f32>(first, name)?.clamp(0.0, 1.0))
+                }
+                "stickers" => {
+                    let dir = second.ok_or(r#"effect "stickers" needs a count and a directory"#)?;
+                    Effect::Stickers(Arc::new(read_stickers(Path::new(dir))?), number::<u32>(first, name)?.min(MAX_STICKERS))
+                }
+                _ => return Err(format!(r#"unknown effect "{}". Presets: {}"#, spec, self.names().join(", ")))
+            };
+            effects.push(effect);
+        }
+        Ok(effects)
+    }
+}
+
+fn number<T: std::str::FromStr>(arg: Option<&str>, effect: &str) -> Result<T, String> {
+    arg.and_then(|a| a.trim().parse().ok()).ok_or_else(|| format!(r#"effect "{}" needs a valid number"#, effect))
+}
+
+/// Guesses the format by the content, not by the extension
+fn open(path: &Path) -> Result<DynamicImage, Box<dyn error::Error>> {
+    Ok(image::load_from_memory(&fs::read(path)?)?)
+}
+
+fn read_stickers(dir: &Path) -> Result<Vec<RgbaImage>, String> {
+    let mut paths: Vec<PathBuf> = dir.read_dir()
+        .map_err(|e| format!("could not read sticker directory {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("png"))
+        .collect();
+    // THE SAME SEED SHOULD PICK THE SAME STICKERS ON EVERY SYSTEM
+    paths.sort();
+
+    let mut stickers = Vec::new();
+    for path in paths {
+        let img = open(&path).map_err(|e| format!("could not read sticker {}: {}", path.display(), e))?;
+        // IMAGES WITHOUT TRANSPARENCY ARE NO STICKERS (E.G. THE RED OVERLAY OF "dank")
+        if img.color().channel_count() == 4 {
+            stickers.push(img.to_rgba());
+        }
+    }
+    if stickers.is_empty() {
+        return Err(format!("no transparent .png stickers in {}", dir.display()));
+    }
+    Ok(stickers)
+}
+
+/// The seed of the random effects. Depends on the content of the image, so the same image always looks the same
+pub fn seed(img: &DynamicImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&img.raw_pixels());
+    hasher.finish()
+}
+
+pub fn apply(img: &DynamicImage, effects: &[Effect], seed: u64) -> DynamicImage {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut img = img.clone();
+    for effect in effects {
+        img = match *effect {
+            Effect::Saturate(factor) => DynamicImage::ImageRgba8(saturate(&img.to_rgba(), factor)),
+            Effect::Contrast(c) => img.adjust_contrast(c),
+            Effect::Brighten(v) => img.brighten(v),
+            Effect::Sharpen => img.unsharpen(1.5, 2),
+            Effect::Jpeg(quality, passes) => jpeg(&img, quality, passes),
+            Effect::Noise(amount) => DynamicImage::ImageRgba8(noise(&img.to_rgba(), amount, &mut rng)),
+            Effect::Pixelate(block) => {
+                let (w, h) = img.dimensions();
+                img.resize_exact((w / block).max(1), (h / block).max(1), FilterType::Triangle)
+                    .resize_exact(w, h, FilterType::Nearest)
+            }
+            Effect::Blur(sigma) => img.blur(sigma),
+            Effect::Invert => {
+                img.invert();
+                img
+            }
+            Effect::Hue(degrees) => img.huerotate(degrees),
+            Effect::Overlay(ref overlay, opacity) => DynamicImage::ImageRgba8(self::overlay(&img.to_rgba(), overlay, opacity)),
+            Effect::Stickers(ref stickers, count) => DynamicImage::ImageRgba8(scatter(&img.to_rgba(), stickers, count, &mut rng)),
+        };
+    }
+    img
+}
+
+fn saturate(img: &RgbaImage, factor: f32) -> RgbaImage {
+    let mut out = img.clone();
+    for pixel in out.pixels_mut() {
+        let luma = pixel.to_luma()[0] as f32;
+        for c in pixel.0.iter_mut().take(3) {
+            *c = (luma + (*c as f32 - luma) * factor).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    out
+}
+
+/// The alpha channel survives, JPEG has none
+fn jpeg(img: &DynamicImage, quality: u8, passes: u32) -> DynamicImage {
+    let original = img.to_rgba();
+    let mut current = DynamicImage::ImageRgb8(img.to_rgb());
+    for _ in 0..passes {
+        let mut buf = Vec::new();
+        let decoded = current.write_to(&mut buf, ImageOutputFormat::JPEG(quality)).ok()
+            .and_then(|_| image::load_from_memory(&buf).ok());
+        match decoded {
+            Some(d) => current = d,
+            None => {
+                warn!("EFFECTS: could not compress image as JPEG");
+                return img.clone();
+            }
+        }
+    }
+
+    let mut out = current.to_rgba();
+    for (pixel, o) in out.pixels_mut().zip(original.pixels()) {
+        pixel[3] = o[3];
+    }
+    DynamicImage::ImageRgba8(out)
+}
+
+fn noise(img: &RgbaImage, amount: u8, rng: &mut StdRng) -> RgbaImage {
+    let amount = amount as i32;
+    let mut out = img.clone();
+    for pixel in out.pixels_mut() {
+        for c in pixel.0.iter_mut().take(3) {
+            *c = (*c as i32 + rng.gen_range(-amount, amount + 1)).clamp(0, 255) as u8;
+        }
+    }
+    out
+}
+
+fn overlay(img: &RgbaImage, overlay: &RgbaImage, opacity: f32) -> RgbaImage {
+    let overlay = image::imageops::resize(overlay, img.width(), img.height(), FilterType::Triangle);
+    let mut out = img.clone();
+    for (pixel, o) in out.pixels_mut().zip(overlay.pixels()) {
+        // NOTHING IS DRAWN ON TRANSPARENT PARTS
+        let alpha = (o[3] as f32 * opacity * pixel[3] as f32 / 255.0).round() as u8;
+        let a = pixel[3];
+        pixel.blend(&image::Rgba([o[0], o[1], o[2], alpha]));
+        pixel[3] = a;
+    }
+    out
+}
+
+fn scatter(img: &RgbaImage, stickers: &[RgbaImage], count: u32, rng: &mut StdRng) -> RgbaImage {
+    let mut out = img.clone();
+    let shorter = img.width().min(img.height()) as f32;
+    // EVERY STICKER IS USED ONCE BEFORE ANY STICKER IS USED TWICE
+    let mut order: Vec<usize> = Vec::new();
+    for _ in 0..count {
+        if order.is_empty() {
+            order = (0..stickers.len()).collect();
+            order.shuffle(rng);
+        }
+        let sticker = &stickers[order.pop().unwrap()];
+        let size = (shorter * rng.gen_range(STICKER_MIN_SIZE, STICKER_MAX_SIZE)).max(1.0);
+        let scale = size / sticker.width().max(sticker.height()) as f32;
+        let (w, h) = (((sticker.width() as f32 * scale) as u32).max(1), ((sticker.height() as f32 * scale) as u32).max(1));
+        let mut sticker = image::imageops::resize(sticker, w, h, FilterType::Triangle);
+        if rng.gen() {
+            sticker = image::imageops::flip_horizontal(&sticker);
+        }
+
+        // STICKERS MAY STICK OUT HALFWAY
+        let x = rng.gen_range(-(w as i64) / 2, img.width() as i64 - w as i64 / 2 + 1);
+        let y = rng.gen_range(-(h as i64) / 2, img.height() as i64 - h as i64 / 2 + 1);
+        for (sx, sy, pixel) in sticker.enumerate_pixels() {
+            let (tx, ty) = (x + sx as i64, y + sy as i64);
+            if tx >= 0 && ty >= 0 && tx < out.width() as i64 && ty < out.height() as i64 {
+                out.get_pixel_mut(tx as u32, ty as u32).blend(pixel);
+            }
+        }
+    }
+    out
+}
+
+/// Every frame gets the same random effects (e.g. the same stickers)
+pub fn apply_frames(frames: &ImageFrames, effects: &[Effect]) -> ImageFrames {
+    if effects.is_empty() {
+        return frames.clone();
+    }
+    let seed = seed(frames.at(&Frame::STILL));
+    frames.map(|f| apply(f, effects, seed))
+}
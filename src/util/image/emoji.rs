@@ -0,0 +1,226 @@
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::RgbaImage;
+use serenity::prelude::Mutex;
+
+use crate::util::image::animation::Frame;
+use crate::util::image::fetch;
+
+const CDN_URL: &str = "https://cdn.discordapp.com/emojis";
+// PER CACHE. EMOJI IMAGES ARE SMALL
+const MAX_CACHED: usize = 512;
+// A TEXT WITH MORE CUSTOM EMOJI SHOWS THE REST AS :name:. EVERY NEW ONE IS A REQUEST TO THE CDN
+const MAX_CUSTOM_PER_TEXT: usize = 16;
+const VARIATION_SELECTOR: char = '\u{fe0f}';
+// EMOJI ARE REPLACED WITH CHARS OF THE PRIVATE USE AREA, SO THE LAYOUT CAN TREAT THEM LIKE ANY OTHER CHAR
+const PLACEHOLDER_START: u32 = 0xE000;
+const PLACEHOLDER_END: u32 = 0xF8FF;
+
+/// Whether the char stands for an emoji image
+pub fn is_placeholder(c: char) -> bool {
+    (PLACEHOLDER_START..=PLACEHOLDER_END).contains(&(c as u32))
+}
+
+/// The index of the emoji in `EmojiText::emoji`
+pub fn placeholder_index(c: char) -> Option<usize> {
+    if is_placeholder(c) { Some((c as u32 - PLACEHOLDER_START) as usize) } else { None }
+}
+
+fn placeholder(index: usize) -> Option<char> {
+    let c = PLACEHOLDER_START + index as u32;
+    if c > PLACEHOLDER_END { None } else { std::char::from_u32(c) }
+}
+
+/// A text whose emoji are replaced with placeholders
+#[derive(Clone, Default)]
+pub struct EmojiText {
+    pub text: String,
+    pub emoji: Vec<Arc<RgbaImage>>,
+}
+
+struct Cache<K> {
+    images: HashMap<K, Arc<RgbaImage>>,
+    // OLDEST FIRST
+    order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone> Cache<K> {
+    fn new() -> Self {
+        Cache {
+            images: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<Arc<RgbaImage>> {
+        self.images.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: K, img: Arc<RgbaImage>) {
+        if self.images.len() >= MAX_CACHED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.images.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.images.insert(key, img);
+    }
+}
+
+/// Finds Unicode and Discord custom emoji in texts. Unicode emoji are read from the emoji directory,
+/// which uses Twemoji's file names (e.g. "1f600.png", "1f468-200d-1f469.png"). Custom emoji are downloaded from the CDN
+pub struct EmojiStore {
+    // SEQUENCE WITHOUT VARIATION SELECTORS -> FILE
+    unicode: HashMap<String, PathBuf>,
+    // NUMBER OF CHARS OF THE LONGEST SEQUENCE
+    longest: usize,
+    unicode_cache: Mutex<Cache<String>>,
+    custom_cache: Mutex<Cache<u64>>,
+}
+
+impl EmojiStore {
+    /// Only indexes the files. The images are read when they are used first
+    pub fn load(path: &Path) -> Result<Self, io::Error> {
+        let mut unicode = HashMap::new();
+        if path.is_dir() {
+            for entry in path.read_dir()? {
+                let path = entry?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("png") {
+                    continue;
+                }
+                let sequence: Option<String> = path.file_stem().and_then(|s| s.to_str()).map(|stem| stem.split('-')
+                    .map(|cp| u32::from_str_radix(cp, 16).ok().and_then(std::char::from_u32))
+                    .collect::<Option<String>>())
+                    .unwrap_or_default();
+                match sequence {
+                    Some(s) => { unicode.insert(strip_variation(&s), path); }
+                    None => warn!("EMOJI: {} is not named after its code points", path.display())
+                }
+            }
+        } else {
+            warn!("EMOJI: emoji directory {} not found. Unicode emoji will be drawn with the fonts", path.display());
+        }
+
+        Ok(EmojiStore {
+            longest: unicode.keys().map(|k| k.chars().count()).max().unwrap_or_default(),
+            unicode,
+            unicode_cache: Mutex::new(Cache::new()),
+            custom_cache: Mutex::new(Cache::new()),
+        })
+    }
+
+    /// Replaces every emoji which can be drawn with a placeholder. Custom emoji which cannot be downloaded become ":name:"
+    pub fn resolve(&self, text: &str) -> EmojiText {
+        lazy_static! {
+            static ref CUSTOM: regex::Regex = regex::Regex::new(r#"<a?:(?P<name>\w+):(?P<id>[0-9]+)>"#).expect("could not compile regex");
+        }
+        // THE TEXT MUST NOT CONTAIN PLACEHOLDERS ALREADY
+        let text: String = text.chars().filter(|c| !is_placeholder(*c)).collect();
+        let mut out = EmojiText::default();
+        let mut last = 0;
+
+        for (custom, cap) in CUSTOM.captures_iter(&text).enumerate() {
+            let m = cap.get(0).unwrap();
+            self.resolve_unicode(&text[last..m.start()], &mut out);
+            last = m.end();
+
+            let img = match cap["id"].parse::<u64>() {
+                Ok(id) if custom < MAX_CUSTOM_PER_TEXT => self.custom(id),
+                _ => None
+            };
+            if !push_emoji(&mut out, img) {
+                out.text.push_str(&format!(":{}:", &cap["name"]));
+            }
+        }
+        self.resolve_unicode(&text[last..], &mut out);
+        out
+    }
+
+    /// Always matches the longest sequence, so e.g. a family is not drawn as three people
+    fn resolve_unicode(&self, text: &str, out: &mut EmojiText) {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        'chars: while i < chars.len() {
+            for len in (1..=self.longest.min(chars.len() - i)).rev() {
+                let sequence: String = chars[i..i + len].iter().collect();
+                let key = strip_variation(&sequence);
+                // PLAIN TEXT LIKE DIGITS, "#" OR "©" HAS EMOJI FILES, TOO
+                if key.is_empty() || (len == 1 && (chars[i] as u32) < 0x2000) || !self.unicode.contains_key(&key) {
+                    continue;
+                }
+                if push_emoji(out, self.unicode(&key)) {
+                    i += len;
+                    if chars.get(i) == Some(&VARIATION_SELECTOR) {
+                        i += 1;
+                    }
+                    continue 'chars;
+                }
+            }
+            out.text.push(chars[i]);
+            i += 1;
+        }
+    }
+
+    fn unicode(&self, key: &str) -> Option<Arc<RgbaImage>> {
+        if let Some(img) = self.unicode_cache.lock().get(&key.to_owned()) {
+            return Some(img);
+        }
+        let path = self.unicode.get(key)?;
+        match image::open(path) {
+            Ok(img) => {
+                let img = Arc::new(img.to_rgba());
+                self.unicode_cache.lock().insert(key.to_owned(), Arc::clone(&img));
+                Some(img)
+            }
+            Err(e) => {
+                warn!("EMOJI: could not read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn custom(&self, id: u64) -> Option<Arc<RgbaImage>> {
+        if let Some(img) = self.custom_cache.lock().get(&id) {
+            return Some(img);
+        }
+        // NOT LOCKED WHILE DOWNLOADING. TWO THREADS MAY DOWNLOAD THE SAME EMOJI, WHICH IS FINE
+        match fetch_custom(id) {
+            Ok(img) => {
+                let img = Arc::new(img);
+                self.custom_cache.lock().insert(id, Arc::clone(&img));
+                Some(img)
+            }
+            Err(e) => {
+                warn!("EMOJI: could not fetch custom emoji {}: {}", id, e);
+                None
+            }
+        }
+    }
+}
+
+/// Animated custom emoji are drawn with their first frame
+fn fetch_custom(id: u64) -> Result<RgbaImage, Box<dyn error::Error>> {
+    let img = fetch::fetch_image(&format!("{}/{}.png?size=64", CDN_URL, id))?;
+    Ok(img.at(&Frame::STILL).to_rgba())
+}
+
+/// False if there is no image or no placeholder left
+fn push_emoji(out: &mut EmojiText, img: Option<Arc<RgbaImage>>) -> bool {
+    match (img, placeholder(out.emoji.len())) {
+        (Some(img), Some(c)) => {
+            out.text.push(c);
+            out.emoji.push(img);
+            true
+        }
+        _ => false
+    }
+}
+
+fn strip_variation(s: &str) -> String {
+    s.chars().filter(|c| *c != VARIATION_SELECTOR).collect()
+}
\ No newline at end of file
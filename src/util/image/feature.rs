@@ -1,9 +1,16 @@
 use image::DynamicImage;
 
-use crate::util::image::gen::{Dimension, FontSettings};
+use crate::util::image::animation::{Animation, Frame, FrameTransform, ImageFrames};
+use crate::util::image::emoji::EmojiText;
+use crate::util::image::gen::{Dimension, FontSettings, ImageStyle};
 
 pub trait Feature {
-    fn apply(&self, bg: &DynamicImage) -> DynamicImage;
+    fn apply(&self, bg: &DynamicImage, frame: &Frame) -> DynamicImage;
+
+    /// The animation of the feature's input, if it has multiple frames
+    fn animation(&self) -> Option<Animation> {
+        None
+    }
 }
 
 
@@ -11,12 +18,14 @@ pub trait Feature {
 pub struct TextFeature {
     pub dimension: Dimension,
     pub font: FontSettings,
-    pub text: String,
+    pub text: EmojiText,
+    pub transform: FrameTransform,
 }
 
 impl Feature for TextFeature {
-    fn apply(&self, bg: &DynamicImage) -> DynamicImage {
-        let img = super::gen::generate_image_text(&self.dimension, &self.font, bg, &self.text);
+    fn apply(&self, bg: &DynamicImage, frame: &Frame) -> DynamicImage {
+        let offset = self.transform.offset(frame, &self.dimension);
+        let img = super::gen::generate_image_text(&self.dimension, offset, &self.font, bg, &self.text);
         DynamicImage::ImageRgba8(img)
     }
 }
@@ -24,14 +33,21 @@ impl Feature for TextFeature {
 #[derive(Clone)]
 pub struct ImageFeature {
     pub dimension: Dimension,
-    pub other: DynamicImage,
+    pub other: ImageFrames,
+    pub transform: FrameTransform,
+    pub style: ImageStyle,
 }
 
 impl Feature for ImageFeature {
-    fn apply(&self, bg: &DynamicImage) -> DynamicImage {
-        let img = super::gen::generate_image_image(&self.dimension, bg, &self.other);
+    fn apply(&self, bg: &DynamicImage, frame: &Frame) -> DynamicImage {
+        let offset = self.transform.offset(frame, &self.dimension);
+        let img = super::gen::generate_image_image(&self.dimension, offset, &self.style, bg, self.other.at(frame));
         DynamicImage::ImageRgba8(img)
     }
+
+    fn animation(&self) -> Option<Animation> {
+        self.other.animation()
+    }
 }
 
 #[derive(PartialEq)]
@@ -0,0 +1,263 @@
+use std::{error, fmt, io};
+use std::fmt::Formatter;
+use std::io::{Cursor, Read};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use image::{ImageError, ImageFormat};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, HOST, LOCATION};
+use reqwest::RedirectPolicy;
+use reqwest::Url;
+
+use crate::util::image::animation::ImageFrames;
+
+// DISCORD'S UPLOAD LIMIT
+pub const MAX_DOWNLOAD_SIZE: u64 = 8 * 1024 * 1024;
+// PER SIDE. EVERYTHING BIGGER IS MOST LIKELY A DECOMPRESSION BOMB
+pub const MAX_DIMENSION: u32 = 4096;
+// A 4096X4096 RGBA IMAGE ALREADY TAKES 64MB
+pub const MAX_PIXELS: u64 = 16_000_000;
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+// WEBP IS LEFT OUT BECAUSE THE IMAGE LIB DOES NOT FULLY SUPPORT IT
+const SUPPORTED_FORMATS: [ImageFormat; 4] = [ImageFormat::PNG, ImageFormat::JPEG, ImageFormat::GIF, ImageFormat::BMP];
+
+lazy_static! {
+    static ref HTTP: reqwest::Client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        // REDIRECTS ARE FOLLOWED BY HAND SO EVERY HOP GETS CHECKED AND PINNED
+        .redirect(RedirectPolicy::none())
+        .build().expect("could not build http client");
+}
+
+/// Everything which can be wrong with an image from the outside. The Display output is meant for the user (e.g. "the image is too large")
+#[derive(Debug)]
+pub enum FetchError {
+    InvalidUrl(String),
+    // THE HOST COULD NOT BE RESOLVED
+    UnknownHost(String),
+    // THE HOST RESOLVES TO A LOOPBACK, PRIVATE OR LINK-LOCAL ADDRESS
+    ForbiddenHost(String),
+    // THE HOST IS NOT REACHABLE OR TIMED OUT
+    Unreachable(reqwest::Error),
+    // HTTP STATUS CODE
+    Status(u16),
+    TooManyRedirects,
+    // THE CONTENT TYPE WHICH WAS SENT INSTEAD
+    NotAnImage(String),
+    TooLarge,
+    UnsupportedFormat,
+    // WIDTH, HEIGHT
+    TooManyPixels(u32, u32),
+    Decode(ImageError),
+}
+
+impl FetchError {
+    /// Whether the user gave a bad image (true) or the source of the image is not working (false)
+    pub fn is_bad_input(&self) -> bool {
+        match *self {
+            Self::Unreachable(_) => false,
+            Self::Status(code) => code < 500,
+            _ => true
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::InvalidUrl(ref url) => write!(f, "``{}`` is not a valid image URL", url),
+            Self::UnknownHost(ref host) => write!(f, "I could not find ``{}``", host),
+            Self::ForbiddenHost(ref host) => write!(f, "I am not allowed to download images from ``{}``", host),
+            Self::Unreachable(_) => write!(f, "I could not download the image"),
+            Self::Status(code) => write!(f, "I could not download the image (HTTP {})", code),
+            Self::TooManyRedirects => write!(f, "the link redirects too often"),
+            Self::NotAnImage(ref content_type) => write!(f, "the link does not lead to an image but to ``{}``", content_type),
+            Self::TooLarge => write!(f, "the image is too large. It may be at most {}MB", MAX_DOWNLOAD_SIZE / 1024 / 1024),
+            Self::UnsupportedFormat => write!(f, "the image format is not supported. Please use PNG, JPEG, GIF or BMP"),
+            Self::TooManyPixels(w, h) => write!(f, "the image is too big ({}x{}). It may be at most {}x{}", w, h, MAX_DIMENSION, MAX_DIMENSION),
+            Self::Decode(_) => write!(f, "I cannot read the image. It may be broken"),
+        }
+    }
+}
+
+impl error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Self::Unreachable(ref e) => Some(e),
+            Self::Decode(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Unreachable(e)
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(e: io::Error) -> Self {
+        FetchError::Decode(ImageError::IoError(e))
+    }
+}
+
+/// Downloads and decodes an image from an untrusted source (e.g. a URL the user sent). Only http and https and public hosts are allowed.
+/// The download stops after MAX_DOWNLOAD_SIZE bytes, even if the server lies about the size
+pub fn fetch_image(url: &str) -> Result<ImageFrames, FetchError> {
+    let parsed = Url::parse(url).map_err(|_| FetchError::InvalidUrl(url.to_owned()))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(FetchError::InvalidUrl(url.to_owned()));
+    }
+
+    let res = get(parsed)?;
+    if !res.status().is_success() {
+        return Err(FetchError::Status(res.status().as_u16()));
+    }
+    let header = |name| res.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_owned());
+    // SOME SERVERS DON'T KNOW WHAT THEY SEND. THE MAGIC BYTES ARE CHECKED ANYWAY
+    if let Some(content_type) = header(CONTENT_TYPE) {
+        if !content_type.starts_with("image/") && !content_type.starts_with("application/octet-stream") {
+            return Err(FetchError::NotAnImage(content_type));
+        }
+    }
+    if header(CONTENT_LENGTH).and_then(|l| l.parse::<u64>().ok()).unwrap_or_default() > MAX_DOWNLOAD_SIZE {
+        return Err(FetchError::TooLarge);
+    }
+
+    let mut buf = Vec::new();
+    res.take(MAX_DOWNLOAD_SIZE + 1).read_to_end(&mut buf)?;
+    if buf.len() as u64 > MAX_DOWNLOAD_SIZE {
+        return Err(FetchError::TooLarge);
+    }
+    decode(&buf)
+}
+
+/// Follows up to MAX_REDIRECTS redirects. Every hop must lead to a public host
+fn get(mut url: Url) -> Result<reqwest::Response, FetchError> {
+    for _ in 0..=MAX_REDIRECTS {
+        let ip = check_host(&url)?;
+        let res = match pin(&url, ip) {
+            Some((pinned, host)) => HTTP.get(pinned).header(HOST, host).send()?,
+            None => HTTP.get(url.clone()).send()?
+        };
+        if !res.status().is_redirection() {
+            return Ok(res);
+        }
+        let location = res.headers().get(LOCATION).and_then(|l| l.to_str().ok()).ok_or_else(|| FetchError::Status(res.status().as_u16()))?;
+        url = url.join(location).map_err(|_| FetchError::InvalidUrl(location.to_owned()))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(FetchError::InvalidUrl(url.to_string()));
+        }
+    }
+    Err(FetchError::TooManyRedirects)
+}
+
+/// Replaces the host of an http URL by the checked address and returns the Host header to send with it.
+/// Otherwise reqwest would resolve the host again and could get a private address (DNS rebinding).
+/// Https URLs are left alone: a rebound address cannot present a valid certificate for the host
+fn pin(url: &Url, ip: IpAddr) -> Option<(Url, String)> {
+    if url.scheme() != "http" {
+        return None;
+    }
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str()?, port),
+        None => url.host_str()?.to_owned()
+    };
+    let mut pinned = url.clone();
+    pinned.set_ip_host(ip).ok()?;
+    Some((pinned, host))
+}
+
+/// Resolves the host of the URL. Fails if any of its addresses is not public, so users cannot make the bot request its own network.
+/// Returns the first address
+fn check_host(url: &Url) -> Result<IpAddr, FetchError> {
+    let host = url.host_str().ok_or_else(|| FetchError::InvalidUrl(url.to_string()))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    // IPV6 HOSTS ARE WRAPPED IN BRACKETS
+    let addresses: Vec<IpAddr> = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => match (host, port).to_socket_addrs() {
+            Ok(k) => k.map(|a| a.ip()).collect(),
+            Err(_) => return Err(FetchError::UnknownHost(host.to_owned()))
+        }
+    };
+    let first = match addresses.first() {
+        Some(ip) => *ip,
+        None => return Err(FetchError::UnknownHost(host.to_owned()))
+    };
+    if !addresses.into_iter().all(is_public) {
+        return Err(FetchError::ForbiddenHost(host.to_owned()));
+    }
+    Ok(first)
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            // 0.0.0.0/8 AND THE SHARED ADDRESS SPACE OF CARRIER-GRADE NAT (100.64.0.0/10)
+            let reserved = a == 0 || (a == 100 && b & 0xC0 == 64);
+            !(reserved || ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_broadcast() || ip.is_multicast() || ip.is_documentation())
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public(IpAddr::V4(v4)),
+            None => !(ip.is_unspecified() || ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local() || ip.is_multicast())
+        }
+    }
+}
+
+/// Checks the format by its magic bytes and the dimensions by the header before the image is decoded
+pub fn decode(buf: &[u8]) -> Result<ImageFrames, FetchError> {
+    let format = image::guess_format(buf).map_err(|_| FetchError::UnsupportedFormat)?;
+    if !SUPPORTED_FORMATS.contains(&format) {
+        return Err(FetchError::UnsupportedFormat);
+    }
+
+    let (w, h) = image::io::Reader::with_format(Cursor::new(buf), format).into_dimensions().map_err(FetchError::Decode)?;
+    if w > MAX_DIMENSION || h > MAX_DIMENSION || w as u64 * h as u64 > MAX_PIXELS {
+        return Err(FetchError::TooManyPixels(w, h));
+    }
+    ImageFrames::decode(buf).map_err(FetchError::Decode)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_addresses() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "172.31.255.255", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1", "::1", "::", "fc00::1", "fd12::1", "fe80::1", "::ffff:127.0.0.1"].iter() {
+            assert!(!is_public(ip.parse().unwrap()), "{} is public", ip);
+        }
+        for ip in ["1.1.1.1", "172.32.0.1", "162.159.128.233", "2606:4700::1111"].iter() {
+            assert!(is_public(ip.parse().unwrap()), "{} is not public", ip);
+        }
+    }
+
+    #[test]
+    fn pins_http_hosts() {
+        let ip = "93.184.216.34".parse().unwrap();
+        let (url, host) = pin(&Url::parse("http://example.com/a.png?size=64").unwrap(), ip).unwrap();
+        assert_eq!(url.as_str(), "http://93.184.216.34/a.png?size=64");
+        assert_eq!(host, "example.com");
+
+        let (url, host) = pin(&Url::parse("http://example.com:8080/a.png").unwrap(), ip).unwrap();
+        assert_eq!(url.as_str(), "http://93.184.216.34:8080/a.png");
+        assert_eq!(host, "example.com:8080");
+
+        assert!(pin(&Url::parse("https://example.com/a.png").unwrap(), ip).is_none());
+    }
+
+    #[test]
+    fn rejects_private_hosts() {
+        for url in ["http://localhost/a.png", "http://127.0.0.1:8080/a.png", "http://[::1]/a.png", "http://169.254.169.254/latest/meta-data"].iter() {
+            match fetch_image(url) {
+                Err(FetchError::ForbiddenHost(_)) => {}
+                other => panic!("expected forbidden host for {}, got {:?}", url, other.err()),
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rusttype::{Font, Scale};
+
+/// Used by text features without a font
+pub const DEFAULT_FONT: &str = "oswald";
+const OSWALD: &[u8] = include_bytes!("Oswald.ttf");
+// TRIED IN THIS ORDER IF THE FONT OF A FEATURE LACKS A GLYPH. MISSING FONTS ARE LEFT OUT
+const FALLBACKS: [&str; 6] = ["oswald", "arial", "segoeuireg", "verdana", "tahoma", "sans"];
+const FONT_EXTENSIONS: [&str; 2] = ["ttf", "otf"];
+
+/// Every font which can be used by templates. Loaded once at startup
+pub struct FontRegistry {
+    fonts: HashMap<String, Font<'static>>,
+}
+
+impl FontRegistry {
+    /// Loads every .ttf and .otf file in the directory. The file name without extension is the font's name.
+    /// Oswald is always available. A missing directory only leaves Oswald
+    pub fn load(path: &Path) -> Result<Self, io::Error> {
+        let mut fonts = HashMap::new();
+        fonts.insert(DEFAULT_FONT.to_owned(), Font::from_bytes(OSWALD).expect("could not read default font"));
+
+        if !path.is_dir() {
+            warn!("FONT REGISTRY: font directory {} not found. Only {} is available", path.display(), DEFAULT_FONT);
+            return Ok(FontRegistry { fonts });
+        }
+
+        for entry in path.read_dir()? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+            if !FONT_EXTENSIONS.contains(&extension.as_str()) {
+                continue;
+            }
+            let name = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(s) => s.to_lowercase(),
+                None => continue
+            };
+
+            match Font::from_bytes(fs::read(&path)?) {
+                Ok(font) => {
+                    if fonts.insert(name.clone(), font).is_some() {
+                        warn!(r#"FONT REGISTRY: font "{}" exists twice. Using {}"#, name, path.display());
+                    }
+                }
+                Err(e) => warn!("FONT REGISTRY: could not read font {}: {}", path.display(), e)
+            }
+        }
+        Ok(FontRegistry { fonts })
+    }
+
+    /// The font followed by the fallback fonts. None if the font does not exist
+    pub fn stack(&self, name: Option<&str>) -> Option<FontStack> {
+        let name = name.unwrap_or(DEFAULT_FONT).to_lowercase();
+        let mut fonts = vec![self.fonts.get(&name)?.clone()];
+        for fallback in FALLBACKS.iter().filter(|f| **f != name) {
+            if let Some(f) = self.fonts.get(*fallback) {
+                fonts.push(f.clone());
+            }
+        }
+        Some(FontStack { fonts })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.fonts.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+}
+
+/// A font and its fallbacks. Cloning is cheap, the font data is shared
+#[derive(Clone, Debug)]
+pub struct FontStack {
+    fonts: Vec<Font<'static>>,
+}
+
+impl FontStack {
+    /// The font chosen by the template. Its metrics are used for the line height
+    pub fn primary(&self) -> &Font<'static> {
+        &self.fonts[0]
+    }
+
+    /// The first font which has a glyph for the char and its position in the stack.
+    /// Falls back to the primary font which draws its "missing glyph" box then
+    pub fn font_for(&self, c: char) -> (usize, &Font<'static>) {
+        // GLYPH 0 IS ".notdef" IN EVERY FONT
+        match self.fonts.iter().enumerate().find(|(_, f)| f.glyph(c).id().0 != 0) {
+            Some(f) => f,
+            None => (0, self.primary())
+        }
+    }
+
+    pub fn advance(&self, c: char, scale: Scale) -> f32 {
+        self.font_for(c).1.glyph(c).scaled(scale).h_metrics().advance_width
+    }
+}
\ No newline at end of file
@@ -1,7 +1,15 @@
-use image::{DynamicImage, FilterType, GenericImage, GenericImageView, Rgba, RgbaImage};
-use imageproc::drawing::{draw_hollow_rect_mut, draw_text_mut};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use image::{DynamicImage, FilterType, GenericImage, GenericImageView, GrayImage, Rgba, RgbaImage};
+use imageproc::drawing::draw_hollow_rect_mut;
 use imageproc::rect::Rect;
-use rusttype::{Font, FontCollection, Scale};
+use rusttype::{point, Scale};
+
+use super::emoji::{self, EmojiText};
+use super::font::FontStack;
+use super::layout::{self, Bounds};
+use super::mask::{self, Border, Mask};
+use super::warp::{self, Quad};
 
 #[derive(Clone, Debug)]
 pub struct Dimension {
@@ -9,140 +17,340 @@ pub struct Dimension {
     pub y: u32,
     pub w: u32,
     pub h: u32,
+    // IF SET THE FEATURE IS WARPED INTO THE QUAD. X, Y, W AND H ARE THE BOX AROUND IT
+    pub quad: Option<Quad>,
 }
 
 #[derive(Clone)]
 pub struct FontSettings {
     pub size: f32,
+    // THE TEXT IS SHRUNK DOWN TO THIS SIZE BEFORE IT IS CUT OFF
+    pub min_size: f32,
     pub color: [u8; 4],
+    pub fonts: FontStack,
+    pub style: TextStyle,
 }
 
-#[cfg(debug_assertions)]
-const DEBUG: bool = true;
-#[cfg(not(debug_assertions))]
-const DEBUG: bool = false;
+/// Everything about the look of a text except its font, size and color
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    pub align: HorizontalAlign,
+    pub vertical_align: VerticalAlign,
+    // DRAWN AROUND EVERY GLYPH (E.G. THE BLACK OUTLINE OF MEME CAPTIONS)
+    pub stroke: Option<Stroke>,
+    pub shadow: Option<Shadow>,
+    // MULTIPLIES THE LINE HEIGHT OF THE FONT
+    pub line_spacing: f32,
+    pub uppercase: bool,
+}
 
-const FONT: &'static [u8] = include_bytes!("Oswald.ttf");
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            align: HorizontalAlign::default(),
+            vertical_align: VerticalAlign::default(),
+            stroke: None,
+            shadow: None,
+            line_spacing: 1.0,
+            uppercase: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum HorizontalAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
 
-fn get_text_width(text: &String, font: &Font, scale: Scale) -> f32 {
-    let words = text.split_ascii_whitespace();
-    let mut width = 0f32;
-    for word in words {
-        let mut word_with = 0f32;
-        for char in word.chars() {
-            word_with += font.glyph(char).scaled(scale).h_metrics().advance_width;
-            //word_with += font.glyph(char).scaled(scale).scale().x
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Clone, Debug)]
+pub struct Stroke {
+    // PIXELS
+    pub width: u32,
+    pub color: [u8; 4],
+}
+
+#[derive(Clone, Debug)]
+pub struct Shadow {
+    // PIXELS. POSITIVE VALUES MOVE THE SHADOW TO THE LOWER RIGHT
+    pub offset: (i32, i32),
+    pub color: [u8; 4],
+}
+
+/// Everything about the look of an image feature except its position
+#[derive(Clone, Debug)]
+pub struct ImageStyle {
+    pub fit: Fit,
+    // WHICH PART OF THE IMAGE STAYS VISIBLE (COVER) OR WHERE THE IMAGE IS PLACED IN THE BOX (CONTAIN)
+    pub anchor: (HorizontalAlign, VerticalAlign),
+    pub filter: FilterType,
+    pub mask: Option<Mask>,
+    pub border: Option<Border>,
+}
+
+impl Default for ImageStyle {
+    fn default() -> Self {
+        ImageStyle {
+            fit: Fit::default(),
+            anchor: (HorizontalAlign::Center, VerticalAlign::Middle),
+            filter: FilterType::Lanczos3,
+            mask: None,
+            border: None,
         }
-        width += word_with;
     }
-    return width;
 }
 
+/// How an image is resized to the box of its feature
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Fit {
+    // FILLS THE BOX. THE PARTS WHICH STICK OUT ARE CUT OFF
+    #[default]
+    Cover,
+    // THE WHOLE IMAGE IS VISIBLE. THE REST OF THE BOX STAYS TRANSPARENT
+    Contain,
+    // FILLS THE BOX AND IGNORES THE ASPECT RATIO
+    Stretch,
+}
+
+// DRAWS THE BOXES OF THE FEATURES. ALWAYS ON IN DEBUG BUILDS
+static DEBUG: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+
+/// Draws the boxes of the features into the rendered images (e.g. to preview templates)
+pub fn set_debug(enabled: bool) {
+    DEBUG.store(enabled, Ordering::Relaxed);
+}
+
+/// The offset moves the text. Text cannot be moved beyond the left or upper edge of the image.
+/// The shadow is drawn first, then the stroke, the text and the emoji on top
+pub fn generate_image_text(dimension: &Dimension, offset: (i32, i32), font_settings: &FontSettings, bg: &DynamicImage, text: &EmojiText) -> RgbaImage {
+    if let Some(ref quad) = dimension.quad {
+        // LAY THE TEXT OUT ON A FLAT LAYER AND WARP THAT LAYER INTO THE QUAD
+        let (w, h) = quad.size();
+        let flat = Dimension { x: 0, y: 0, w, h, quad: None };
+        let layer = generate_image_text(&flat, (0, 0), font_settings, &DynamicImage::ImageRgba8(RgbaImage::new(w, h)), text);
 
-pub fn generate_image_text(dimension: &Dimension, font_settings: &FontSettings, bg: &DynamicImage, text: &str) -> RgbaImage {
-    let text = text.to_owned();
+        let mut img = RgbaImage::new(bg.width(), bg.height());
+        copy_image(bg, &mut img);
+        warp::warp_into_quad(&layer, &mut img, &quad.translate(offset.0 as f32, offset.1 as f32));
+        return img;
+    }
+    let style = &font_settings.style;
+    let emoji_images = &text.emoji;
+    let text = if style.uppercase { text.text.to_uppercase() } else { text.text.to_owned() };
+    // THE STROKE MUST NOT BE CUT OFF AT THE EDGES OF THE BOX
+    let stroke_width = style.stroke.as_ref().map(|s| s.width).unwrap_or_default();
+    let max_width = dimension.w.saturating_sub(2 * stroke_width);
 
     let mut img = RgbaImage::new(bg.width(), bg.height());
     copy_image(bg, &mut img);
 
-    let font = FontCollection::from_bytes(&FONT).expect("could not read font").into_font().unwrap();
-    let font_size = font_settings.size;
-    let mut scale = Scale {
-        x: font_size,
-        y: font_size,
-    };
+    let font = &font_settings.fonts;
 
-    if DEBUG {
+    if DEBUG.load(Ordering::Relaxed) {
         draw_hollow_rect_mut(&mut img, Rect::at(dimension.x as i32, dimension.y as i32).of_size(dimension.w, dimension.h), Rgba([0, 255, 0, 255]));
     }
 
-    let mut final_font_height: f32;
-    let mut lines: Vec<String>;
-    let mut counter = 0;
-    loop {
-        final_font_height = font.v_metrics(scale).ascent - font.v_metrics(scale).descent;
-        lines = Vec::new();
-        lines.clear();
-
-        if get_text_width(&text, &font, scale) as u32 > dimension.w {
-            let words = text.split_ascii_whitespace();
-            let mut t = String::new();
-            let mut current_line_width = 0f32;
-            for word in words {
-                let word = format!("{} ", word);
-                let mut word_width = 0f32;
-                for char in word.chars() {
-                    word_width += font.glyph(char).scaled(scale).h_metrics().advance_width;
-                }
-                current_line_width += word_width;
-
-                if current_line_width.ceil() as u32 > dimension.w {
-                    current_line_width = word_width;
-                    lines.push(t);
-                    t = String::new();
-                }
-                t.push_str(&word);
-            }
-            lines.push(t);
-        } else {
-            lines.push(text.to_owned());
-        }
+    let layout = layout::layout(&text, font, &Bounds {
+        width: max_width as f32,
+        height: dimension.h as f32,
+        size: font_settings.size,
+        min_size: font_settings.min_size,
+        line_spacing: style.line_spacing,
+    });
+    let scale = Scale::uniform(layout.size);
+
+    let x = (dimension.x as i32 + offset.0).max(0) as f32 + stroke_width as f32;
+    let y = (dimension.y as i32 + offset.1).max(0) as f32;
+    let y = y + match style.vertical_align {
+        VerticalAlign::Top => 0.0,
+        VerticalAlign::Middle => (dimension.h as f32 - layout.height) / 2.0,
+        VerticalAlign::Bottom => dimension.h as f32 - layout.height,
+    };
 
-        if scale.y * lines.len() as f32 > dimension.h as f32 {
-            scale = Scale {
-                x: scale.x * 0.99f32,
-                y: scale.y * 0.99f32,
-            };
-            counter += 1;
-        } else {
-            info!("Scaled: {} times", counter);
-            break;
+    // THE GLYPHS ARE DRAWN INTO A MASK FIRST, SO THE STROKE AND THE SHADOW CAN BE DERIVED FROM IT
+    let mut mask = GrayImage::new(img.width(), img.height());
+    let mut emoji_positions = Vec::new();
+    for (i, line) in layout.lines.iter().enumerate() {
+        let free = max_width as f32 - line.width;
+        let line_x = x + match style.align {
+            HorizontalAlign::Left => 0.0,
+            HorizontalAlign::Center => free / 2.0,
+            HorizontalAlign::Right => free,
+        };
+        draw_text(&mut mask, &mut emoji_positions, line_x, y + layout.line_height * i as f32, scale, font, &line.text);
+    }
+
+    let outline = style.stroke.as_ref().map(|s| mask::dilate(&mask, s.width));
+    if let Some(ref shadow) = style.shadow {
+        blend_mask(&mut img, outline.as_ref().unwrap_or(&mask), shadow.color, shadow.offset);
+    }
+    if let (Some(outline), Some(stroke)) = (outline.as_ref(), style.stroke.as_ref()) {
+        blend_mask(&mut img, outline, stroke.color, (0, 0));
+    }
+    blend_mask(&mut img, &mask, font_settings.color, (0, 0));
+
+    let emoji_size = layout.size.round() as u32;
+    for (index, ex, ey) in emoji_positions {
+        if let Some(e) = emoji_images.get(index) {
+            let e = image::imageops::resize(e.as_ref(), emoji_size, emoji_size, FilterType::Triangle);
+            copy_image_with_offset(&e, &mut img, ex.round() as i64, ey.round() as i64, emoji_size, emoji_size);
         }
     }
 
-// SCALE TEXT DOWN
-    /*let line_height = font_height;
-    let total_height = line_height * lines.len() as f32;
-    if total_height.ceil() as u32 > dimension.h {
-        let scale_down_percentage = dimension.h as f32 / total_height;
-        scale = Scale {
-            x: scale.x * scale_down_percentage,
-            y: scale.y * scale_down_percentage,
+    img
+}
+
+/// Like imageproc's draw_text_mut, but every char is drawn with the first font of the stack which has a glyph for it.
+/// Draws the coverage of the glyphs into the mask. Emoji are not drawn, their index and upper left corner are collected instead
+fn draw_text(mask: &mut GrayImage, emoji_positions: &mut Vec<(usize, f32, f32)>, x: f32, y: f32, scale: Scale, fonts: &FontStack, text: &str) {
+    let v_metrics = fonts.primary().v_metrics(scale);
+    let mut caret = 0f32;
+    let mut last = None;
+    for c in text.chars() {
+        // EMOJI ARE AS WIDE AND AS HIGH AS THE FONT SIZE AND CENTERED IN THE LINE
+        if let Some(index) = emoji::placeholder_index(c) {
+            let line_height = v_metrics.ascent - v_metrics.descent;
+            emoji_positions.push((index, x + caret, y + (line_height - scale.y) / 2.0));
+            caret += scale.x;
+            last = None;
+            continue;
         }
-    }*/
+        let (index, font) = fonts.font_for(c);
+        let glyph = font.glyph(c).scaled(scale);
+        // KERNING ONLY WORKS BETWEEN GLYPHS OF THE SAME FONT
+        if let Some((last_index, last_id)) = last {
+            if last_index == index {
+                caret += font.pair_kerning(scale, last_id, glyph.id());
+            }
+        }
+        last = Some((index, glyph.id()));
 
+        let advance = glyph.h_metrics().advance_width;
+        let glyph = glyph.positioned(point(x + caret, y + v_metrics.ascent));
+        caret += advance;
 
-    for (i, line) in lines.into_iter().enumerate() {
-        draw_text_mut(&mut img, Rgba(font_settings.color), dimension.x, (dimension.y as f32 + final_font_height * i as f32) as u32, scale, &font, &line);
+        let bb = match glyph.pixel_bounding_box() {
+            Some(bb) => bb,
+            None => continue
+        };
+        glyph.draw(|gx, gy, gv| {
+            let ix = gx as i32 + bb.min.x;
+            let iy = gy as i32 + bb.min.y;
+            if ix >= 0 && iy >= 0 && ix < mask.width() as i32 && iy < mask.height() as i32 {
+                let pixel = mask.get_pixel_mut(ix as u32, iy as u32);
+                pixel[0] = pixel[0].max((gv * 255.0).round() as u8);
+            }
+        });
     }
+}
 
-    img
+/// Blends the color onto the image wherever the mask is set. The mask value scales the alpha of the color
+fn blend_mask(img: &mut RgbaImage, mask: &GrayImage, color: [u8; 4], offset: (i32, i32)) {
+    for (x, y, pixel) in mask.enumerate_pixels() {
+        if pixel[0] == 0 {
+            continue;
+        }
+        let (tx, ty) = (x as i32 + offset.0, y as i32 + offset.1);
+        if tx >= 0 && ty >= 0 && tx < img.width() as i32 && ty < img.height() as i32 {
+            let alpha = (color[3] as u32 * pixel[0] as u32 / 255) as u8;
+            img.blend_pixel(tx as u32, ty as u32, Rgba([color[0], color[1], color[2], alpha]));
+        }
+    }
 }
 
-pub fn generate_image_image(dimension: &Dimension, bg: &DynamicImage, other: &DynamicImage) -> RgbaImage {
+/// The offset moves the image. Parts which are moved out of the image are cut off.
+/// The mask and the border are applied after the image is fitted into the feature
+pub fn generate_image_image(dimension: &Dimension, offset: (i32, i32), style: &ImageStyle, bg: &DynamicImage, other: &DynamicImage) -> RgbaImage {
     let mut img = RgbaImage::new(bg.width(), bg.height());
     copy_image(bg, &mut img);
-    let other = other.resize(dimension.w, dimension.h, FilterType::Nearest);
-    copy_image_with_offset(&other, &mut img, dimension.x, dimension.y, dimension.w, dimension.h);
-    if DEBUG {
+    let shaped = style.mask.is_some() || style.border.is_some();
+    if let Some(ref quad) = dimension.quad {
+        let quad = quad.translate(offset.0 as f32, offset.1 as f32);
+        let (w, h) = quad.size();
+        let mut other = fit(other, w, h, style);
+        if shaped {
+            mask::cut_out(&mut other, style.mask.as_ref(), style.border.as_ref());
+        }
+        warp::warp_into_quad(&other, &mut img, &quad);
+        if DEBUG.load(Ordering::Relaxed) {
+            warp::draw_quad(&mut img, &quad, Rgba([0, 255, 0, 255]));
+        }
+        return img;
+    }
+    let mut other = fit(other, dimension.w, dimension.h, style);
+    if shaped {
+        mask::cut_out(&mut other, style.mask.as_ref(), style.border.as_ref());
+    }
+    copy_image_with_offset(&other, &mut img, dimension.x as i64 + offset.0 as i64, dimension.y as i64 + offset.1 as i64, dimension.w, dimension.h);
+    if DEBUG.load(Ordering::Relaxed) {
         draw_hollow_rect_mut(&mut img, Rect::at(dimension.x as i32, dimension.y as i32).of_size(dimension.w, dimension.h), Rgba([0, 255, 0, 255]));
     }
     img
 }
 
+/// Resizes the image to exactly the given size
+fn fit(other: &DynamicImage, w: u32, h: u32, style: &ImageStyle) -> RgbaImage {
+    let (iw, ih) = other.dimensions();
+    if style.fit == Fit::Stretch || iw == 0 || ih == 0 || w == 0 || h == 0 {
+        return other.resize_exact(w, h, style.filter).to_rgba();
+    }
+
+    let (scale_x, scale_y) = (w as f32 / iw as f32, h as f32 / ih as f32);
+    let (scale, (sw, sh)) = match style.fit {
+        Fit::Cover => {
+            let scale = scale_x.max(scale_y);
+            (scale, ((iw as f32 * scale).round().max(w as f32) as u32, (ih as f32 * scale).round().max(h as f32) as u32))
+        }
+        _ => {
+            let scale = scale_x.min(scale_y);
+            (scale, ((iw as f32 * scale).round().clamp(1.0, w as f32) as u32, (ih as f32 * scale).round().clamp(1.0, h as f32) as u32))
+        }
+    };
+    // DON'T RESAMPLE IF THE SIZE DOES NOT CHANGE
+    let mut scaled = if scale == 1.0 { other.to_rgba() } else { other.resize_exact(sw, sh, style.filter).to_rgba() };
+
+    // THE SPACE LEFT IN THE BOX (CONTAIN) OR THE PART WHICH IS CUT OFF (COVER)
+    let (free_x, free_y) = (sw.abs_diff(w), sh.abs_diff(h));
+    let x = match style.anchor.0 {
+        HorizontalAlign::Left => 0,
+        HorizontalAlign::Center => free_x / 2,
+        HorizontalAlign::Right => free_x,
+    };
+    let y = match style.anchor.1 {
+        VerticalAlign::Top => 0,
+        VerticalAlign::Middle => free_y / 2,
+        VerticalAlign::Bottom => free_y,
+    };
+
+    if style.fit == Fit::Cover {
+        return image::imageops::crop(&mut scaled, x, y, w, h).to_image();
+    }
+    let mut out = RgbaImage::new(w, h);
+    image::imageops::replace(&mut out, &scaled, x, y);
+    out
+}
+
 fn copy_image<S: GenericImage<Pixel=D::Pixel>, D: GenericImage>(source: &S, dest: &mut D) {
     copy_image_with_offset(source, dest, 0, 0, source.width(), source.height());
 }
 
-fn copy_image_with_offset<S: GenericImage<Pixel=D::Pixel>, D: GenericImage>(source: &S, dest: &mut D, offset_x: u32, offset_y: u32, width_x: u32, width_y: u32) {
+fn copy_image_with_offset<S: GenericImage<Pixel=D::Pixel>, D: GenericImage>(source: &S, dest: &mut D, offset_x: i64, offset_y: i64, width_x: u32, width_y: u32) {
     for (x, y, pixel) in source.pixels() {
-        let x1 = x + offset_x;
-        let y1 = y + offset_y;
+        let x1 = x as i64 + offset_x;
+        let y1 = y as i64 + offset_y;
 
-        if x <= width_x && y <= width_y {
-            if x1 < dest.width() && y1 < dest.height() {
-                dest.blend_pixel(x1, y1, pixel);
-            }
+        if x <= width_x && y <= width_y && x1 >= 0 && y1 >= 0 && x1 < dest.width() as i64 && y1 < dest.height() as i64 {
+            dest.blend_pixel(x1 as u32, y1 as u32, pixel);
         }
     }
 }
\ No newline at end of file
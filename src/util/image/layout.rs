@@ -0,0 +1,280 @@
+use rusttype::Scale;
+
+use super::emoji;
+use super::font::FontStack;
+
+/// Text is never drawn smaller than this unless the template asks for a smaller font size
+pub const MIN_FONT_SIZE: f32 = 12.0;
+// THE BINARY SEARCH STOPS ONCE THE FONT SIZE IS THIS PRECISE
+const SIZE_PRECISION: f32 = 0.5;
+const ELLIPSIS: &str = "…";
+
+/// Measures text. Implemented by the fonts and by fixed-width fonts in tests
+pub trait Metrics {
+    fn advance(&self, c: char, size: f32) -> f32;
+    fn kerning(&self, first: char, second: char, size: f32) -> f32;
+    /// Ascent minus descent
+    fn height(&self, size: f32) -> f32;
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let mut width = 0.0;
+        let mut last = None;
+        for c in text.chars() {
+            if let Some(l) = last {
+                width += self.kerning(l, c, size);
+            }
+            width += self.advance(c, size);
+            last = Some(c);
+        }
+        width
+    }
+}
+
+impl Metrics for FontStack {
+    // EMOJI ARE SQUARES AS WIDE AS THE FONT SIZE
+    fn advance(&self, c: char, size: f32) -> f32 {
+        if emoji::is_placeholder(c) {
+            return size;
+        }
+        FontStack::advance(self, c, Scale::uniform(size))
+    }
+
+    fn kerning(&self, first: char, second: char, size: f32) -> f32 {
+        if emoji::is_placeholder(first) || emoji::is_placeholder(second) {
+            return 0.0;
+        }
+        let (first_index, font) = self.font_for(first);
+        // KERNING ONLY WORKS BETWEEN GLYPHS OF THE SAME FONT
+        if self.font_for(second).0 != first_index {
+            return 0.0;
+        }
+        font.pair_kerning(Scale::uniform(size), first, second)
+    }
+
+    fn height(&self, size: f32) -> f32 {
+        let v_metrics = self.primary().v_metrics(Scale::uniform(size));
+        v_metrics.ascent - v_metrics.descent
+    }
+}
+
+/// Where the text has to fit in
+#[derive(Clone, Debug)]
+pub struct Bounds {
+    pub width: f32,
+    pub height: f32,
+    // THE PREFERRED FONT SIZE
+    pub size: f32,
+    pub min_size: f32,
+    pub line_spacing: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub text: String,
+    pub width: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub size: f32,
+    pub lines: Vec<Line>,
+    // DISTANCE BETWEEN THE TOPS OF TWO LINES
+    pub line_height: f32,
+    // FROM THE TOP OF THE FIRST LINE TO THE BOTTOM OF THE LAST LINE
+    pub height: f32,
+}
+
+/// Breaks the text into lines using the largest font size between the minimum and the preferred size which fits.
+/// Words longer than a line are broken. If the text does not fit at the minimum size, the lines which do not fit
+/// are dropped and the last line ends with an ellipsis
+pub fn layout<M: Metrics>(text: &str, metrics: &M, bounds: &Bounds) -> Layout {
+    let min_size = bounds.min_size.min(bounds.size);
+    let fits = |size: f32| {
+        let lines = wrap(text, metrics, size, bounds.width).len();
+        text_height(metrics, size, bounds.line_spacing, lines) <= bounds.height
+    };
+
+    let size = if fits(bounds.size) {
+        bounds.size
+    } else if !fits(min_size) {
+        return ellipsize(text, metrics, min_size, bounds);
+    } else {
+        // FITS AT LOW, DOES NOT FIT AT HIGH
+        let (mut low, mut high) = (min_size, bounds.size);
+        while high - low > SIZE_PRECISION {
+            let mid = (low + high) / 2.0;
+            if fits(mid) { low = mid; } else { high = mid; }
+        }
+        low
+    };
+
+    let lines = wrap(text, metrics, size, bounds.width);
+    build(lines, metrics, size, bounds)
+}
+
+fn ellipsize<M: Metrics>(text: &str, metrics: &M, size: f32, bounds: &Bounds) -> Layout {
+    let mut lines = wrap(text, metrics, size, bounds.width);
+    let mut count = lines.len();
+    while count > 1 && text_height(metrics, size, bounds.line_spacing, count) > bounds.height {
+        count -= 1;
+    }
+    lines.truncate(count);
+
+    if let Some(last) = lines.last_mut() {
+        let mut chars: Vec<char> = last.text.chars().collect();
+        loop {
+            let candidate = format!("{}{}", chars.iter().collect::<String>().trim_end(), ELLIPSIS);
+            if chars.is_empty() || metrics.width(&candidate, size) <= bounds.width {
+                last.width = metrics.width(&candidate, size);
+                last.text = candidate;
+                break;
+            }
+            chars.pop();
+        }
+    }
+    build(lines, metrics, size, bounds)
+}
+
+fn build<M: Metrics>(lines: Vec<Line>, metrics: &M, size: f32, bounds: &Bounds) -> Layout {
+    Layout {
+        size,
+        line_height: metrics.height(size) * bounds.line_spacing,
+        height: text_height(metrics, size, bounds.line_spacing, lines.len()),
+        lines,
+    }
+}
+
+fn text_height<M: Metrics>(metrics: &M, size: f32, line_spacing: f32, lines: usize) -> f32 {
+    if lines == 0 {
+        return 0.0;
+    }
+    metrics.height(size) * (1.0 + line_spacing * (lines - 1) as f32)
+}
+
+/// Greedy line breaking. Explicit newlines always start a new line
+fn wrap<M: Metrics>(text: &str, metrics: &M, size: f32, max_width: f32) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if current.is_empty() { word.to_owned() } else { format!("{} {}", current, word) };
+            if metrics.width(&candidate, size) <= max_width {
+                current = candidate;
+                continue;
+            }
+
+            if !current.is_empty() {
+                lines.push(line(current, metrics, size));
+            }
+            current = String::new();
+            for c in word.chars() {
+                current.push(c);
+                // EVERY LINE GETS AT LEAST ONE CHAR, EVEN IF IT IS TOO WIDE
+                if current.chars().count() > 1 && metrics.width(&current, size) > max_width {
+                    current.pop();
+                    lines.push(line(current, metrics, size));
+                    current = c.to_string();
+                }
+            }
+        }
+        lines.push(line(current, metrics, size));
+    }
+    lines
+}
+
+fn line<M: Metrics>(text: String, metrics: &M, size: f32) -> Line {
+    Line {
+        width: metrics.width(&text, size),
+        text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every char is half as wide as the font size, the line height is the font size
+    struct Monospace {
+        kerning: f32,
+    }
+
+    impl Metrics for Monospace {
+        fn advance(&self, _c: char, size: f32) -> f32 {
+            size / 2.0
+        }
+
+        fn kerning(&self, _first: char, _second: char, _size: f32) -> f32 {
+            self.kerning
+        }
+
+        fn height(&self, size: f32) -> f32 {
+            size
+        }
+    }
+
+    const MONO: Monospace = Monospace { kerning: 0.0 };
+
+    fn bounds(width: f32, height: f32) -> Bounds {
+        Bounds { width, height, size: 20.0, min_size: 10.0, line_spacing: 1.0 }
+    }
+
+    fn texts(layout: &Layout) -> Vec<&str> {
+        layout.lines.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_size_if_it_fits() {
+        let layout = layout("hello world", &MONO, &bounds(200.0, 20.0));
+        assert_eq!(layout.size, 20.0);
+        assert_eq!(texts(&layout), vec!["hello world"]);
+        assert_eq!(layout.lines[0].width, 110.0);
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let layout = layout("hello world", &MONO, &bounds(60.0, 40.0));
+        assert_eq!(layout.size, 20.0);
+        assert_eq!(texts(&layout), vec!["hello", "world"]);
+        assert_eq!(layout.height, 40.0);
+    }
+
+    #[test]
+    fn keeps_explicit_newlines() {
+        let layout = layout("a\n\nb", &MONO, &bounds(200.0, 60.0));
+        assert_eq!(texts(&layout), vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn breaks_long_words() {
+        // 6 CHARS FIT IN A LINE AT SIZE 20
+        let layout = layout("abcdefghijklmn", &MONO, &Bounds { min_size: 20.0, ..bounds(60.0, 60.0) });
+        assert_eq!(texts(&layout), vec!["abcdef", "ghijkl", "mn"]);
+    }
+
+    #[test]
+    fn shrinks_until_it_fits() {
+        // ONE LINE FITS UP TO SIZE 60 / 5.5, TWO LINES ONLY UP TO SIZE 10
+        let layout = layout("hello world", &MONO, &bounds(60.0, 20.0));
+        assert_eq!(texts(&layout), vec!["hello world"]);
+        assert!(layout.lines[0].width <= 60.0);
+        assert!((layout.size - 60.0 / 5.5).abs() <= SIZE_PRECISION);
+    }
+
+    #[test]
+    fn ellipsizes_at_min_size() {
+        // 10 CHARS FIT IN A LINE AT SIZE 10, ONE LINE FITS
+        let layout = layout("the quick brown fox jumps", &MONO, &bounds(50.0, 15.0));
+        assert_eq!(layout.size, 10.0);
+        assert_eq!(texts(&layout), vec!["the quick…"]);
+        assert!(layout.lines[0].width <= 50.0);
+    }
+
+    #[test]
+    fn counts_kerning() {
+        let kerned = Monospace { kerning: -2.0 };
+        assert_eq!(kerned.width("abc", 20.0), 26.0);
+        // 6 CHARS ARE 50 PIXELS WIDE WITH KERNING
+        let layout = layout("abcdef", &kerned, &Bounds { min_size: 20.0, ..bounds(50.0, 20.0) });
+        assert_eq!(texts(&layout), vec!["abcdef"]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use image::{DynamicImage, FilterType, GrayImage, Luma, Pixel, Rgba, RgbaImage};
+
+/// Cuts a shape out of an image feature
+#[derive(Clone, Debug)]
+pub enum Mask {
+    // AN ELLIPSE IF THE BOX IS NOT SQUARE
+    Circle,
+    // CORNER RADIUS IN PIXELS
+    RoundedRect(u32),
+    // THE ALPHA CHANNEL OF AN IMAGE. OPAQUE IMAGES USE THEIR BRIGHTNESS INSTEAD
+    Image(Arc<GrayImage>),
+}
+
+impl Mask {
+    /// The alpha mask of an image. Images without transparency are used as grayscale masks (white = visible)
+    pub fn from_image(img: &DynamicImage) -> Mask {
+        let rgba = img.to_rgba();
+        let mask = if rgba.pixels().all(|p| p[3] == 255) {
+            img.to_luma()
+        } else {
+            GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| Luma([rgba.get_pixel(x, y)[3]]))
+        };
+        Mask::Image(Arc::new(mask))
+    }
+
+    /// Renders the mask at the size of the image. Edges are anti-aliased
+    pub fn render(&self, width: u32, height: u32) -> GrayImage {
+        match *self {
+            Mask::Circle => {
+                let (rx, ry) = (width as f32 / 2.0, height as f32 / 2.0);
+                GrayImage::from_fn(width, height, |x, y| {
+                    let (dx, dy) = ((x as f32 + 0.5 - rx) / rx, (y as f32 + 0.5 - ry) / ry);
+                    // DISTANCE TO THE EDGE IN PIXELS, ROUGHLY
+                    let inside = (1.0 - (dx * dx + dy * dy).sqrt()) * rx.min(ry);
+                    Luma([coverage(inside)])
+                })
+            }
+            Mask::RoundedRect(radius) => {
+                let r = (radius as f32).min(width as f32 / 2.0).min(height as f32 / 2.0);
+                GrayImage::from_fn(width, height, |x, y| {
+                    let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                    let dx = (r - px).max(px - (width as f32 - r)).max(0.0);
+                    let dy = (r - py).max(py - (height as f32 - r)).max(0.0);
+                    Luma([coverage(r - (dx * dx + dy * dy).sqrt())])
+                })
+            }
+            Mask::Image(ref mask) => image::imageops::resize(mask.as_ref(), width, height, FilterType::Triangle)
+        }
+    }
+}
+
+/// Drawn along the inner edge of the mask (or of the box if there is no mask)
+#[derive(Clone, Debug)]
+pub struct Border {
+    // PIXELS
+    pub width: u32,
+    pub color: [u8; 4],
+}
+
+/// 0 outside, 255 inside, anti-aliased in between
+fn coverage(inside: f32) -> u8 {
+    ((inside + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Applies the mask to the alpha channel of the image and draws the border on top of it
+pub fn cut_out(img: &mut RgbaImage, mask: Option<&Mask>, border: Option<&Border>) {
+    let shape = match mask {
+        Some(m) => m.render(img.width(), img.height()),
+        None => GrayImage::from_pixel(img.width(), img.height(), Luma([255])),
+    };
+    for (pixel, m) in img.pixels_mut().zip(shape.pixels()) {
+        pixel[3] = (pixel[3] as u32 * m[0] as u32 / 255) as u8;
+    }
+
+    if let Some(border) = border {
+        let inner = erode(&shape, border.width);
+        for ((pixel, s), i) in img.pixels_mut().zip(shape.pixels()).zip(inner.pixels()) {
+            let ring = s[0].saturating_sub(i[0]);
+            if ring == 0 {
+                continue;
+            }
+            let c = border.color;
+            // THE IMAGE IS ALREADY CUT OUT. THE RING MUST NOT BE MORE VISIBLE THAN THE SHAPE
+            let alpha = (c[3] as u32 * ring as u32 / 255) as u8;
+            pixel.blend(&Rgba([c[0], c[1], c[2], alpha]));
+        }
+    }
+}
+
+/// Grows the mask by the radius in every direction
+pub fn dilate(mask: &GrayImage, radius: u32) -> GrayImage {
+    let disk = disk(radius);
+    let mut out = mask.clone();
+    for (x, y, pixel) in mask.enumerate_pixels() {
+        if pixel[0] == 0 {
+            continue;
+        }
+        for (dx, dy) in disk.iter() {
+            let (ox, oy) = (x as i32 + dx, y as i32 + dy);
+            if ox >= 0 && oy >= 0 && ox < out.width() as i32 && oy < out.height() as i32 {
+                let Luma([o]) = out.get_pixel_mut(ox as u32, oy as u32);
+                *o = (*o).max(pixel[0]);
+            }
+        }
+    }
+    out
+}
+
+/// Shrinks the mask by the radius in every direction. Everything outside of the mask counts as empty
+pub fn erode(mask: &GrayImage, radius: u32) -> GrayImage {
+    let disk = disk(radius);
+    let r = radius as i32;
+    let (w, h) = (mask.width() as i32, mask.height() as i32);
+    let mut out = mask.clone();
+    for y in -r..h + r {
+        for x in -r..w + r {
+            let inside = x >= 0 && y >= 0 && x < w && y < h;
+            let value = if inside { mask.get_pixel(x as u32, y as u32)[0] } else { 0 };
+            if value == 255 {
+                continue;
+            }
+            for (dx, dy) in disk.iter() {
+                let (ox, oy) = (x + dx, y + dy);
+                if ox >= 0 && oy >= 0 && ox < w && oy < h {
+                    let Luma([o]) = out.get_pixel_mut(ox as u32, oy as u32);
+                    *o = (*o).min(value);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn disk(radius: u32) -> Vec<(i32, i32)> {
+    let r = radius as i32;
+    (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| dx * dx + dy * dy <= r * r)
+        .collect()
+}
\ No newline at end of file
@@ -1,41 +1,74 @@
 use std::error;
 use std::path::Path;
+use std::sync::Arc;
 
-use image::{DynamicImage, ImageFormat};
+use image::{DynamicImage, FilterType, GenericImageView, ImageFormat};
 
+pub use effects::{Effect, EffectRegistry};
+pub use emoji::EmojiStore;
+pub use font::{FontRegistry, FontStack};
 pub use gen::Dimension;
-pub use gen::FontSettings;
+pub use gen::set_debug;
+pub use gen::{FontSettings, Fit, HorizontalAlign, ImageStyle, Shadow, Stroke, TextStyle, VerticalAlign};
+pub use mask::{Border, Mask};
+pub use warp::Quad;
 
-use crate::util::image::feature::Feature;
+use crate::util::image::animation::{Animation, Frame, FrameTransform, ImageFrames};
+use crate::util::image::feature::{Feature, ImageFeature};
 use crate::util::image::partial::{PartialFeature, PartialTemplate};
 
+pub mod effects;
+mod emoji;
+pub mod fetch;
+mod font;
 mod gen;
+mod layout;
+mod mask;
 mod parser;
+pub mod animation;
+mod warp;
 pub mod partial;
 pub mod feature;
 
 pub struct ImageStorage {
     storage: Vec<PartialTemplate>,
+    effects: EffectRegistry,
+    // OF THE TEMPLATE PARSER. SKIPPED TEMPLATES AND IGNORED ATTRIBUTES
+    warnings: Vec<String>,
 }
 
 impl ImageStorage {
+    /// Loads the fonts in the "fonts" subdirectory and the effect presets first. Templates may use them by their name
     pub fn load(p: &Path) -> Result<Self, Box<dyn error::Error>> {
-        let templates = match parser::parse(p) {
+        let fonts = FontRegistry::load(&p.join("fonts"))?;
+        info!("Loaded fonts: {}", fonts.names().join(", "));
+        let emoji = Arc::new(EmojiStore::load(&p.join("emoji"))?);
+        let effects = EffectRegistry::load(p);
+        info!("Loaded effect presets: {}", effects.names().join(", "));
+        let mut warnings = Vec::new();
+        let templates = match parser::parse(p, &fonts, &emoji, &effects, &mut warnings) {
             Ok(k) => k,
             Err(e) => {
                 return Err(Box::new(e));
             }
         };
         Ok(Self {
-            storage: templates
+            storage: templates,
+            effects,
+            warnings,
         })
     }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The effects of a preset like "deepfry". None if the preset does not exist
+    pub fn effect_preset(&self, name: &str) -> Option<&[Effect]> {
+        self.effects.preset(name)
+    }
     pub fn start_building(&self, key: &str) -> Option<PartialTemplate> {
-        let storage = &self.storage;
-        match storage.iter().find(|t| t.key == key) {
-            Some(s) => Some(s.clone()),
-            None => None
-        }
+        self.storage.iter().find(|t| t.key == key).cloned()
     }
 
     pub fn get_all_keys(&self) -> Vec<String> {
@@ -47,24 +80,27 @@ impl ImageStorage {
         ret
     }
 
+    /// Whether the template may only be used in NSFW channels
+    pub fn is_nsfw(&self, key: &str) -> bool {
+        self.storage.iter().any(|t| t.key == key && t.nsfw)
+    }
+
     /// Returns None if the key cannot be found in the Vec
-    #[allow(dead_code)]
     pub fn get_required_features_with_duplicates(&self, key: &str) -> Option<Vec<PartialFeature>> {
-        let pt = match self.storage.iter().find(|t| t.key == key) {
-            Some(s) => s,
-            None => return None
-        };
+        let pt = self.storage.iter().find(|t| t.key == key)?;
 
         Some(pt.features.clone())
     }
 
+    /// Width and height of the base image. None if the key cannot be found
+    pub fn base_size(&self, key: &str) -> Option<(u32, u32)> {
+        self.storage.iter().find(|t| t.key == key).map(|t| t.base.dimensions())
+    }
+
     /// Returns None if the key cannot be found
     /// Ignores duplicates (mostly helpful for help messages)
     pub fn get_required_features(&self, key: &str) -> Option<Vec<PartialFeature>> {
-        let pt = match self.storage.iter().find(|t| t.key == key) {
-            Some(s) => s,
-            None => return None
-        };
+        let pt = self.storage.iter().find(|t| t.key == key)?;
 
         let mut v = Vec::new();
 
@@ -84,19 +120,78 @@ pub struct Template {
     pub name: String,
     pub base: DynamicImage,
     pub features: Vec<Box<dyn Feature + Send + Sync>>,
+    pub animation: Option<Animation>,
 }
 
 impl Template {
+    /// A template which only consists of the image. Used to render images which were edited without a template
+    pub fn from_image(name: String, img: ImageFrames) -> Template {
+        let first = img.at(&Frame::STILL);
+        let (w, h) = (first.width(), first.height());
+        Template {
+            name,
+            base: DynamicImage::ImageRgba8(image::RgbaImage::new(w, h)),
+            features: vec![Box::new(ImageFeature {
+                dimension: Dimension { x: 0, y: 0, w, h, quad: None },
+                other: img,
+                transform: FrameTransform::default(),
+                style: ImageStyle::default(),
+            })],
+            animation: None,
+        }
+    }
+
+    /// Renders a PNG or a GIF if the template or one of its inputs is animated.
+    /// Every second frame is dropped until the GIF is small enough to be uploaded. If two frames are still too large,
+    /// they are scaled down. Fails with OutputTooLarge if the GIF does not fit even then
     pub fn apply(&self) -> Result<Vec<u8>, Box<dyn error::Error>> {
-        let mut last: DynamicImage = self.features[0].apply(&self.base);
+        let animation = match self.timeline() {
+            Some(a) => a,
+            None => {
+                let mut buf: Vec<u8> = Vec::new();
+                self.render(&Frame::STILL).write_to(&mut buf, ImageFormat::PNG)?;
+                return Ok(buf);
+            }
+        };
 
-        for feature in self.features.iter().skip(1) {
-            last = feature.apply(&last);
+        let mut frames: Vec<image::RgbaImage> = (0..animation.frames)
+            .map(|index| self.render(&Frame { index, count: animation.frames, time: index * animation.delay }).to_rgba())
+            .collect();
+        let mut delay = animation.delay;
+        loop {
+            let buf = animation::encode_gif(frames.clone(), delay)?;
+            if buf.len() <= animation::MAX_OUTPUT_SIZE {
+                return Ok(buf);
+            }
+
+            if frames.len() > 2 {
+                frames = frames.into_iter().step_by(2).collect();
+                delay *= 2;
+                continue;
+            }
+            let (w, h) = (frames[0].width() / 2, frames[0].height() / 2);
+            if w < animation::MIN_OUTPUT_SIDE || h < animation::MIN_OUTPUT_SIDE {
+                return Err(Box::new(animation::OutputTooLarge));
+            }
+            frames = frames.iter().map(|f| image::imageops::resize(f, w, h, FilterType::Triangle)).collect();
         }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.timeline().is_some()
+    }
 
-        let mut buf: Vec<u8> = Vec::new();
-        last.write_to(&mut buf, ImageFormat::PNG)?;
+    fn timeline(&self) -> Option<Animation> {
+        let inputs: Vec<Animation> = self.features.iter().filter_map(|f| f.animation()).collect();
+        Animation::combine(self.animation.as_ref(), &inputs, self.base.width(), self.base.height())
+    }
 
-        Ok(buf)
+    fn render(&self, frame: &Frame) -> DynamicImage {
+        let mut last: DynamicImage = self.features[0].apply(&self.base, frame);
+
+        for feature in self.features.iter().skip(1) {
+            last = feature.apply(&last, frame);
+        }
+        last
     }
 }
\ No newline at end of file
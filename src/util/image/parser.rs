@@ -1,16 +1,29 @@
 use std::{error, fmt, fs, io};
 use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
 
+use image::FilterType;
 use serde::export::Formatter;
 
-use crate::util::image::Dimension;
+use crate::util::image::animation::{self, Animation, FrameTransform};
+use crate::util::image::{Border, Dimension, EffectRegistry, EmojiStore, Fit, FontRegistry, HorizontalAlign, ImageStyle, Mask, Quad, Shadow, Stroke, TextStyle, VerticalAlign};
 use crate::util::image::feature::FeatureType;
 use crate::util::image::partial::{PartialFeature, PartialTemplate};
 
-const IMAGE_EXTENSIONS: [&'static str; 3] = [".jpg", ".jpeg", ".png"];
+const IMAGE_EXTENSIONS: [&str; 3] = [".jpg", ".jpeg", ".png"];
 
-pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
+// LOGS THE WARNING AND KEEPS IT, SO IT CAN BE SHOWN TO WHOEVER RELOADS THE TEMPLATES
+macro_rules! parse_warn {
+    ($warnings:expr, $($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        warn!("TEMPLATE PARSER: {}", message);
+        $warnings.push(message);
+    }};
+}
+
+/// Every skipped template and ignored attribute is added to the warnings
+pub fn parse(path: &Path, fonts: &FontRegistry, emoji: &Arc<EmojiStore>, effects: &EffectRegistry, warnings: &mut Vec<String>) -> Result<Vec<PartialTemplate>, Error> {
     if !path.is_dir() {
         return Err(Error::PathNotDir);
     }
@@ -24,7 +37,7 @@ pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
         let entry = entry?;
 
         if entry.path().is_dir() {
-            ret.append(&mut parse(entry.path().as_path())?);
+            ret.append(&mut parse(entry.path().as_path(), fonts, emoji, effects, warnings)?);
             continue;
         }
 
@@ -58,7 +71,7 @@ pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
         let mut toml_file = match fs::File::open(toml_file_path) {
             Ok(k) => k,
             Err(e) => {
-                warn!("TEMPLATE PARSER: could not open metadata file: {}", e);
+                parse_warn!(warnings, "could not open metadata file: {}", e);
                 continue;
             }
         };
@@ -68,11 +81,29 @@ pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
         let metadata: TemplateMetadataFile = match toml::from_str(&toml_file_content) {
             Ok(k) => k,
             Err(e) => {
-                warn!(r#"TEMPLATE PARSER: template file "{}" could not be parsed! Error: {}"#, &file_name, e);
+                parse_warn!(warnings, r#"template file "{}" could not be parsed! Error: {}"#, &file_name, e);
                 continue 'tomlLoop; // SKIP TEMPLATE
             }
         };
 
+        let animation = match metadata.animation {
+            Some(ref a) => {
+                if a.frames == 0 || a.frames > animation::MAX_FRAMES {
+                    parse_warn!(warnings, r#"template "{}" must have between 1 and {} frames"#, &metadata.name, animation::MAX_FRAMES);
+                    continue 'tomlLoop; // SKIP THIS TEMPLATE
+                }
+                if a.delay < animation::MIN_DELAY {
+                    parse_warn!(warnings, r#"the frame delay of template "{}" must be at least {}ms"#, &metadata.name, animation::MIN_DELAY);
+                    continue 'tomlLoop; // SKIP THIS TEMPLATE
+                }
+                Some(Animation {
+                    frames: a.frames,
+                    delay: a.delay,
+                })
+            }
+            None => None
+        };
+
         let mut features: Vec<PartialFeature> = Vec::new();
 
         for feat in metadata.features {
@@ -82,56 +113,122 @@ pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
                 "image" => FeatureType::Image,
                 "user_image" => FeatureType::UserImage,
                 _ => {
-                    return Err(Error::InvalidFeatureType);
+                    parse_warn!(warnings, r#"feature "{}" in template file "{}" has the unknown kind "{}""#, feat.key, &file_name, feat.kind);
+                    continue 'tomlLoop; // SKIP THIS TEMPLATE
                 }
             };
 
-            if let Some(f) = features.iter().find(|f| &f.key == &feat.key) {
+            if let Some(f) = features.iter().find(|f| f.key == feat.key) {
                 if f.kind != kind {
-                    warn!(r#"TEMPLATE PARSER: template "{}" has at least two features with the same key but with different types!"#, &metadata.name);
+                    parse_warn!(warnings, r#"template "{}" has at least two features with the same key but with different types!"#, &metadata.name);
                     continue 'tomlLoop;
                 }
             }
 
+            let mut font = None;
+            let mut text_style = TextStyle::default();
+            let mut image_style = ImageStyle::default();
+            let mut image_effects = Vec::new();
             // Check if required attributes exist
             match kind {
                 FeatureType::Text | FeatureType::SplitText => {
                     let mut skip = false;
                     if feat.font_color.is_none() {
-                        warn!(r#"TEMPLATE PARSER: missing attribute "{}" for feature "{}" in template "{}" "#, "font_color", feat.key, &metadata.name);
+                        parse_warn!(warnings, r#"missing attribute "{}" for feature "{}" in template "{}" "#, "font_color", feat.key, &metadata.name);
                         skip = true;
                     }
 
                     if feat.font_size.is_none() {
-                        warn!(r#"TEMPLATE PARSER: missing attribute "{}" for feature "{}" in template "{}" "#, "font_size", feat.key, &metadata.name);
+                        parse_warn!(warnings, r#"missing attribute "{}" for feature "{}" in template "{}" "#, "font_size", feat.key, &metadata.name);
                         skip = true;
                     }
 
+                    font = fonts.stack(feat.font.as_deref());
+                    if font.is_none() {
+                        parse_warn!(warnings, r#"unknown font "{}" for feature "{}" in template "{}". Available fonts: {}"#, feat.font.as_deref().unwrap_or_default(), feat.key, &metadata.name, fonts.names().join(", "));
+                        skip = true;
+                    }
+
+                    match text_style_of(&feat) {
+                        Ok(s) => text_style = s,
+                        Err(e) => {
+                            parse_warn!(warnings, r#"feature "{}" in template "{}" has an invalid style: {}"#, feat.key, &metadata.name, e);
+                            skip = true;
+                        }
+                    }
+
                     if skip {
                         continue 'tomlLoop; // SKIP THIS TEMPLATE
                     }
                 },
                 FeatureType::Image => {
                     if feat.overlay_image_path.is_none() {
-                        warn!(r#"TEMPLATE PARSER: missing attribute "{}" for feature "{}" in template "{}" "#, "overlay_image_path", feat.key, &metadata.name);
+                        parse_warn!(warnings, r#"missing attribute "{}" for feature "{}" in template "{}" "#, "overlay_image_path", feat.key, &metadata.name);
                         continue 'tomlLoop; // SKIP THIS TEMPLATE
                     }
                 },
                 FeatureType::UserImage => {
-                    if feat.default_user.unwrap_or_default() == true {
-                        if features.len() != 0 {
-                            warn!(r#"TEMPLATE PARSER: user_image feature "{}" in template "{}" with attribute default_user = true must be the first feature!"#, feat.key, &metadata.name);
-                            continue 'tomlLoop; // SKIP THIS TEMPLATE
-                        }
+                    if feat.default_user.unwrap_or_default() && !features.is_empty() {
+                        parse_warn!(warnings, r#"user_image feature "{}" in template "{}" with attribute default_user = true must be the first feature!"#, feat.key, &metadata.name);
+                        continue 'tomlLoop; // SKIP THIS TEMPLATE
                     }
                 }
             }
 
-            let dimension = Dimension {
-                x: feat.x,
-                y: feat.y,
-                w: feat.w,
-                h: feat.h,
+            if kind == FeatureType::Image || kind == FeatureType::UserImage {
+                match image_style_of(&feat) {
+                    Ok(s) => image_style = s,
+                    Err(e) => {
+                        parse_warn!(warnings, r#"feature "{}" in template "{}" has an invalid style: {}"#, feat.key, &metadata.name, e);
+                        continue 'tomlLoop; // SKIP THIS TEMPLATE
+                    }
+                }
+                match effects.parse(feat.effects.as_deref().unwrap_or_default()) {
+                    Ok(e) => image_effects = e,
+                    Err(e) => {
+                        parse_warn!(warnings, r#"feature "{}" in template "{}" has invalid effects: {}"#, feat.key, &metadata.name, e);
+                        continue 'tomlLoop; // SKIP THIS TEMPLATE
+                    }
+                }
+            } else if feat.effects.is_some() {
+                parse_warn!(warnings, r#"feature "{}" in template "{}" has effects but only image features support them. They will be ignored"#, feat.key, &metadata.name);
+            }
+
+            if animation.is_none() && (feat.jitter.is_some() || feat.offsets.is_some()) {
+                parse_warn!(warnings, r#"feature "{}" in template "{}" has per-frame transforms but the template is not animated. They will be ignored"#, feat.key, &metadata.name);
+            }
+
+            // CORNERS TAKE PRECEDENCE OVER THE BOX
+            let quad = match (feat.corners, feat.x, feat.y, feat.w, feat.h) {
+                (Some(c), _, _, _, _) => Some(Quad([(c[0][0], c[0][1]), (c[1][0], c[1][1]), (c[2][0], c[2][1]), (c[3][0], c[3][1])])),
+                // ONLY ROTATED BOXES NEED TO BE WARPED
+                (None, Some(x), Some(y), Some(w), Some(h)) => feat.rotation.map(|_| Quad::from_box(x, y, w, h)),
+                _ => {
+                    parse_warn!(warnings, r#"feature "{}" in template "{}" needs either x, y, w and h or corners"#, feat.key, &metadata.name);
+                    continue 'tomlLoop; // SKIP THIS TEMPLATE
+                }
+            };
+            let quad = match (quad, feat.rotation) {
+                (Some(q), Some(r)) => Some(q.rotate(r)),
+                (q, _) => q
+            };
+
+            let dimension = match quad {
+                Some(q) => {
+                    if !q.is_valid() {
+                        parse_warn!(warnings, r#"the corners of feature "{}" in template "{}" do not form a convex quadrilateral"#, feat.key, &metadata.name);
+                        continue 'tomlLoop; // SKIP THIS TEMPLATE
+                    }
+                    let (x, y, w, h) = q.bounds();
+                    Dimension { x, y, w, h, quad: Some(q) }
+                }
+                None => Dimension {
+                    x: feat.x.unwrap_or_default(),
+                    y: feat.y.unwrap_or_default(),
+                    w: feat.w.unwrap_or_default(),
+                    h: feat.h.unwrap_or_default(),
+                    quad: None,
+                }
             };
 
             features.push(PartialFeature {
@@ -139,17 +236,28 @@ pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
                 kind,
                 dimension,
                 font_size: feat.font_size,
+                min_font_size: feat.min_font_size,
                 font_color: feat.font_color,
+                font,
+                text_style,
+                image_style,
+                effects: image_effects,
                 overlay_image_path: feat.overlay_image_path,
                 default_user: feat.default_user,
                 grayscale: feat.grayscale,
+                transform: FrameTransform {
+                    jitter: feat.jitter.unwrap_or_default(),
+                    offsets: feat.offsets.unwrap_or_default(),
+                },
             });
         }
 
 
         let base_img;
 
-        if metadata.empty.is_none() {
+        if let Some(empty) = metadata.empty {
+            base_img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(empty.w, empty.h));
+        } else {
             let mut base_img_file = None;
             'extLoop: for extension in IMAGE_EXTENSIONS.iter() {
                 let base_img_path = Path::new(path.as_os_str()).join(format!("{}{}", file_name, extension));
@@ -165,37 +273,145 @@ pub fn parse(path: &Path) -> Result<Vec<PartialTemplate>, Error> {
             }
 
             if base_img_file.is_none() {
-                warn!("TEMPLATE PARSER: could not find base image to metadata file {}", &file_name);
+                parse_warn!(warnings, "could not find base image to metadata file {}", &file_name);
                 continue;
             }
             let mut base_img_file = base_img_file.unwrap();
 
             let mut base_img_buf = Vec::new();
-            base_img_file.read_to_end(&mut base_img_buf)?;
+            if let Err(e) = base_img_file.read_to_end(&mut base_img_buf) {
+                parse_warn!(warnings, "could not read base image to metadata file {}: {}", &file_name, e);
+                continue 'tomlLoop; // SKIP THIS TEMPLATE
+            }
 
-            base_img = image::load_from_memory(&base_img_buf)?;
-        } else {
-            let empty = metadata.empty.unwrap();
-            base_img = image::DynamicImage::ImageRgba8(image::RgbaImage::new(empty.w, empty.h));
+            base_img = match image::load_from_memory(&base_img_buf) {
+                Ok(k) => k,
+                Err(e) => {
+                    parse_warn!(warnings, "could not decode base image to metadata file {}: {}", &file_name, e);
+                    continue 'tomlLoop; // SKIP THIS TEMPLATE
+                }
+            };
         }
 
         ret.push(PartialTemplate::new(
             metadata.name,
             base_img,
-            features));
+            features,
+            metadata.nsfw.unwrap_or_default(),
+            animation,
+            Arc::clone(emoji)));
     }
 
     Ok(ret)
 }
 
+fn text_style_of(feat: &TemplateFileFeature) -> Result<TextStyle, String> {
+    let align = match feat.align.as_deref() {
+        None | Some("left") => HorizontalAlign::Left,
+        Some("center") => HorizontalAlign::Center,
+        Some("right") => HorizontalAlign::Right,
+        Some(a) => return Err(format!(r#"unknown align "{}""#, a))
+    };
+    let vertical_align = match feat.vertical_align.as_deref() {
+        None | Some("top") => VerticalAlign::Top,
+        Some("middle") => VerticalAlign::Middle,
+        Some("bottom") => VerticalAlign::Bottom,
+        Some(a) => return Err(format!(r#"unknown vertical_align "{}""#, a))
+    };
+    let line_spacing = feat.line_spacing.unwrap_or(1.0);
+    if line_spacing <= 0.0 {
+        return Err("line_spacing must be greater than 0".to_owned());
+    }
+
+    Ok(TextStyle {
+        align,
+        vertical_align,
+        stroke: feat.stroke_width.filter(|w| *w > 0).map(|width| Stroke {
+            width,
+            color: feat.stroke_color.unwrap_or([0, 0, 0, 255]),
+        }),
+        shadow: feat.shadow_offset.map(|[x, y]| Shadow {
+            offset: (x, y),
+            color: feat.shadow_color.unwrap_or([0, 0, 0, 128]),
+        }),
+        line_spacing,
+        uppercase: feat.uppercase.unwrap_or_default(),
+    })
+}
+
+/// Mask images are read right away, so broken paths are noticed at startup
+fn image_style_of(feat: &TemplateFileFeature) -> Result<ImageStyle, String> {
+    let mask = match feat.mask.as_deref() {
+        None => None,
+        Some("circle") => Some(Mask::Circle),
+        Some("rounded") => match feat.mask_radius {
+            Some(r) => Some(Mask::RoundedRect(r)),
+            None => return Err(r#"mask "rounded" needs a mask_radius"#.to_owned())
+        },
+        Some(path) => match image::open(path) {
+            Ok(img) => Some(Mask::from_image(&img)),
+            Err(e) => return Err(format!(r#"could not read mask image "{}": {}"#, path, e))
+        }
+    };
+
+    let fit = match feat.fit.as_deref() {
+        None | Some("cover") => Fit::Cover,
+        Some("contain") => Fit::Contain,
+        Some("stretch") => Fit::Stretch,
+        Some(f) => return Err(format!(r#"unknown fit "{}""#, f))
+    };
+    let anchor = match feat.anchor.as_deref() {
+        None | Some("center") => (HorizontalAlign::Center, VerticalAlign::Middle),
+        Some("top") => (HorizontalAlign::Center, VerticalAlign::Top),
+        Some("bottom") => (HorizontalAlign::Center, VerticalAlign::Bottom),
+        Some("left") => (HorizontalAlign::Left, VerticalAlign::Middle),
+        Some("right") => (HorizontalAlign::Right, VerticalAlign::Middle),
+        Some("top_left") => (HorizontalAlign::Left, VerticalAlign::Top),
+        Some("top_right") => (HorizontalAlign::Right, VerticalAlign::Top),
+        Some("bottom_left") => (HorizontalAlign::Left, VerticalAlign::Bottom),
+        Some("bottom_right") => (HorizontalAlign::Right, VerticalAlign::Bottom),
+        Some(a) => return Err(format!(r#"unknown anchor "{}""#, a))
+    };
+    let filter = match feat.filter.as_deref() {
+        None | Some("lanczos") => FilterType::Lanczos3,
+        Some("catmullrom") => FilterType::CatmullRom,
+        Some("gaussian") => FilterType::Gaussian,
+        Some("triangle") => FilterType::Triangle,
+        Some("nearest") => FilterType::Nearest,
+        Some(f) => return Err(format!(r#"unknown filter "{}""#, f))
+    };
+
+    Ok(ImageStyle {
+        fit,
+        anchor,
+        filter,
+        mask,
+        border: feat.border_width.filter(|w| *w > 0).map(|width| Border {
+            width,
+            color: feat.border_color.unwrap_or([255, 255, 255, 255]),
+        }),
+    })
+}
+
 #[derive(Serialize, Deserialize)]
 struct TemplateMetadataFile {
     name: String,
     #[serde(default)]
+    nsfw: Option<bool>,
+    #[serde(default)]
     empty: Option<TemplateFileEmpty>,
+    #[serde(default)]
+    animation: Option<TemplateFileAnimation>,
     features: Vec<TemplateFileFeature>,
 }
 
+#[derive(Serialize, Deserialize)]
+struct TemplateFileAnimation {
+    frames: u32,
+    // MILLISECONDS
+    delay: u32,
+}
+
 #[derive(Serialize, Deserialize)]
 struct TemplateFileEmpty {
     w: u32,
@@ -206,38 +422,96 @@ struct TemplateFileEmpty {
 struct TemplateFileFeature {
     key: String,
     kind: String,
-    x: u32,
-    y: u32,
-    w: u32,
-    h: u32,
+    #[serde(default)]
+    x: Option<u32>,
+    #[serde(default)]
+    y: Option<u32>,
+    #[serde(default)]
+    w: Option<u32>,
+    #[serde(default)]
+    h: Option<u32>,
+    // UPPER LEFT, UPPER RIGHT, LOWER RIGHT, LOWER LEFT
+    #[serde(default)]
+    corners: Option<[[f32; 2]; 4]>,
+    // DEGREES, CLOCKWISE
+    #[serde(default)]
+    rotation: Option<f32>,
     #[serde(default)]
     font_size: Option<f32>,
     #[serde(default)]
+    min_font_size: Option<f32>,
+    #[serde(default)]
     font_color: Option<[u8; 4]>,
+    // NAME OF A FONT IN templates/fonts WITHOUT EXTENSION
+    #[serde(default)]
+    font: Option<String>,
+    // LEFT, CENTER OR RIGHT
+    #[serde(default)]
+    align: Option<String>,
+    // TOP, MIDDLE OR BOTTOM
+    #[serde(default)]
+    vertical_align: Option<String>,
+    #[serde(default)]
+    stroke_width: Option<u32>,
+    #[serde(default)]
+    stroke_color: Option<[u8; 4]>,
+    // X, Y
+    #[serde(default)]
+    shadow_offset: Option<[i32; 2]>,
+    #[serde(default)]
+    shadow_color: Option<[u8; 4]>,
+    #[serde(default)]
+    line_spacing: Option<f32>,
+    #[serde(default)]
+    uppercase: Option<bool>,
     #[serde(default)]
     overlay_image_path: Option<String>,
     #[serde(default)]
     default_user: Option<bool>,
     #[serde(default)]
     grayscale: Option<bool>,
+    // COVER, CONTAIN OR STRETCH
+    #[serde(default)]
+    fit: Option<String>,
+    // E.G. CENTER, TOP OR BOTTOM_RIGHT
+    #[serde(default)]
+    anchor: Option<String>,
+    // LANCZOS, CATMULLROM, GAUSSIAN, TRIANGLE OR NEAREST
+    #[serde(default)]
+    filter: Option<String>,
+    // "circle", "rounded" OR THE PATH TO A MASK IMAGE
+    #[serde(default)]
+    mask: Option<String>,
+    // CORNER RADIUS OF "rounded" MASKS
+    #[serde(default)]
+    mask_radius: Option<u32>,
+    #[serde(default)]
+    border_width: Option<u32>,
+    #[serde(default)]
+    border_color: Option<[u8; 4]>,
+    // E.G. "deepfry" OR "blur:2". APPLIED IN ORDER
+    #[serde(default)]
+    effects: Option<Vec<String>>,
+    #[serde(default)]
+    jitter: Option<[u32; 2]>,
+    #[serde(default)]
+    offsets: Option<Vec<[i32; 2]>>,
 }
 
 #[derive(Debug)]
 pub enum Error {
     PathNotDir,
-    InvalidFeatureType,
-    IoError(io::Error),
-    ImageError(image::ImageError),
+    Io(io::Error),
+    Image(image::ImageError),
     Other(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match *self {
-            Self::InvalidFeatureType => write!(f, "feature type/kind is unknown"),
             Self::PathNotDir => write!(f, "path ist not a directory"),
-            Self::IoError(ref e) => e.fmt(f),
-            Self::ImageError(ref e) => e.fmt(f),
+            Self::Io(ref e) => e.fmt(f),
+            Self::Image(ref e) => e.fmt(f),
             Self::Other(ref e) => write!(f, "{}", e),
         }
     }
@@ -246,10 +520,9 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            Self::InvalidFeatureType => None,
             Self::PathNotDir => None,
-            Self::IoError(ref e) => Some(e),
-            Self::ImageError(ref e) => Some(e),
+            Self::Io(ref e) => Some(e),
+            Self::Image(ref e) => Some(e),
             Self::Other(_) => None
         }
     }
@@ -257,12 +530,12 @@ impl error::Error for Error {
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
-        Error::IoError(e)
+        Error::Io(e)
     }
 }
 
 impl From<image::ImageError> for Error {
     fn from(e: image::ImageError) -> Self {
-        Error::ImageError(e)
+        Error::Image(e)
     }
 }
\ No newline at end of file
@@ -1,12 +1,20 @@
+use std::sync::Arc;
+
 use image::DynamicImage;
 
-use crate::util::image::{Dimension, FontSettings};
+use crate::util::image::{Dimension, Effect, FontSettings, FontStack, ImageStyle, TextStyle};
+use crate::util::image::animation::{Animation, FrameTransform, ImageFrames};
+use crate::util::image::effects;
+use crate::util::image::emoji::EmojiStore;
 use crate::util::image::feature::FeatureType;
 
 pub struct PartialTemplate {
     pub(super) key: String,
     pub(super) base: DynamicImage,
     pub(super) features: Vec<PartialFeature>,
+    pub(super) nsfw: bool,
+    pub(super) animation: Option<Animation>,
+    pub(super) emoji: Arc<EmojiStore>,
     pub(super) built_features: Vec<Box<dyn super::feature::Feature + Send + Sync>>,
 }
 
@@ -16,22 +24,34 @@ pub struct PartialFeature {
     pub kind: FeatureType,
     pub dimension: Dimension,
     pub font_size: Option<f32>,
+    pub min_font_size: Option<f32>,
     pub font_color: Option<[u8; 4]>,
+    // THE FONT AND ITS FALLBACKS. SET FOR EVERY TEXT FEATURE
+    pub font: Option<FontStack>,
+    pub text_style: TextStyle,
+    pub image_style: ImageStyle,
+    // APPLIED TO THE IMAGE OF IMAGE FEATURES BEFORE IT IS PLACED
+    pub effects: Vec<Effect>,
     pub overlay_image_path: Option<String>,
     pub default_user: Option<bool>,
     pub grayscale: Option<bool>,
+    pub transform: FrameTransform,
 }
 
 impl PartialTemplate {
-    pub fn new(key: String, base: DynamicImage, features: Vec<PartialFeature>) -> Self {
+    pub fn new(key: String, base: DynamicImage, features: Vec<PartialFeature>, nsfw: bool, animation: Option<Animation>, emoji: Arc<EmojiStore>) -> Self {
         Self {
             key,
             base,
             features,
+            nsfw,
+            animation,
+            emoji,
             built_features: vec![],
         }
     }
 
+    /// Emoji in the text are drawn as images. Custom emoji are downloaded if they are not cached
     pub fn set_text(&mut self, key: &str, text: String) -> Result<(), error::Error> {
         let pfeatures: Vec<PartialFeature> = self.features.iter().filter(|tp| tp.key == key).cloned().collect();
         self.features.retain(|f| pfeatures.iter().any(|pf| pf.key != f.key));
@@ -39,35 +59,41 @@ impl PartialTemplate {
         if pfeatures.is_empty() {
             return Err(error::Error::KeyNotFound);
         }
+        let text = self.emoji.resolve(&text);
 
         for f in pfeatures {
             if f.kind != FeatureType::Text && f.kind != FeatureType::SplitText {
                 return Err(error::Error::WrongType);
             }
 
-            let font_size = match f.font_size {
-                Some(s) => s,
-                None => 24f32
-            };
+            let font_size = f.font_size.unwrap_or(24f32);
+            let min_font_size = f.min_font_size.unwrap_or(super::layout::MIN_FONT_SIZE).min(font_size);
 
-            let font_color = match f.font_color {
+            let font_color = f.font_color.unwrap_or([255, 255, 255, 255]);
+
+            let fonts = match f.font {
                 Some(s) => s,
-                None => [255, 255, 255, 255]
+                None => return Err(error::Error::FeatureAttributeMissing("font"))
             };
             self.built_features.push(Box::new(super::feature::TextFeature {
                 dimension: f.dimension.clone(),
                 font: FontSettings {
                     size: font_size,
+                    min_size: min_font_size,
                     color: font_color,
+                    fonts,
+                    style: f.text_style.clone(),
                 },
                 text: text.clone(),
+                transform: f.transform.clone(),
             }));
         }
 
         Ok(())
     }
 
-    pub fn set_user_image(&mut self, key: &str, other: DynamicImage) -> Result<(), error::Error> {
+    /// Animated images (e.g. animated avatars) turn the whole template into an animation
+    pub fn set_user_image(&mut self, key: &str, other: ImageFrames) -> Result<(), error::Error> {
         let pfeatures: Vec<PartialFeature> = self.features.iter().filter(|tp| tp.key == key).cloned().collect();
         self.features.retain(|f| pfeatures.iter().any(|pf| pf.key != f.key));
 
@@ -80,16 +106,14 @@ impl PartialTemplate {
                 return Err(error::Error::WrongType);
             }
 
-            let img: DynamicImage;
-            if f.grayscale.unwrap_or_default() == true {
-                img = other.grayscale();
-            } else {
-                img = other.clone();
-            }
+            let img = if f.grayscale.unwrap_or_default() { other.map(|i| i.grayscale()) } else { other.clone() };
+            let img = effects::apply_frames(&img, &f.effects);
 
             self.built_features.push(Box::new(super::feature::ImageFeature {
                 dimension: f.dimension.clone(),
                 other: img,
+                transform: f.transform.clone(),
+                style: f.image_style.clone(),
             }));
         }
 
@@ -119,26 +143,28 @@ impl PartialTemplate {
 
             let path = std::path::Path::new(&path);
             if !path.exists() {
-                return Err(error::Error::AttributeError("overlay_image_path", "overlay image not found"));
+                return Err(error::Error::InvalidAttribute("overlay_image_path", "overlay image not found"));
             }
 
             let file_buf = match std::fs::read(path) {
                 Ok(k) => k,
-                Err(e) => return Err(error::Error::IoError("overlay_image_path", e))
+                Err(e) => return Err(error::Error::Io("overlay_image_path", e))
             };
 
             let mut img = match image::load_from_memory(&file_buf) {
                 Ok(k) => k,
-                Err(_) => return Err(error::Error::AttributeError("overlay_image_path", "could not load image with library"))
+                Err(_) => return Err(error::Error::InvalidAttribute("overlay_image_path", "could not load image with library"))
             };
 
-            if f.grayscale.unwrap_or_default() == true {
+            if f.grayscale.unwrap_or_default() {
                 img = img.grayscale();
             }
 
             self.built_features.push(Box::new(super::feature::ImageFeature {
                 dimension: f.dimension.clone(),
-                other: img,
+                other: effects::apply_frames(&ImageFrames::still(img), &f.effects),
+                transform: f.transform.clone(),
+                style: f.image_style.clone(),
             }));
         }
 
@@ -155,6 +181,7 @@ impl PartialTemplate {
             name: self.key,
             base: self.base,
             features: self.built_features,
+            animation: self.animation,
         })
     }
 }
@@ -165,6 +192,9 @@ impl Clone for PartialTemplate {
             key: self.key.clone(),
             base: self.base.clone(),
             features: self.features.clone(),
+            nsfw: self.nsfw,
+            animation: self.animation.clone(),
+            emoji: Arc::clone(&self.emoji),
             built_features: vec![], // LEAVE BLANK
         }
     }
@@ -184,9 +214,9 @@ mod error {
         FeatureAttributeMissing(&'static str),
 
         // Attribute name, Error msg
-        AttributeError(&'static str, &'static str),
+        InvalidAttribute(&'static str, &'static str),
         // Attribute name, Error
-        IoError(&'static str, std::io::Error),
+        Io(&'static str, std::io::Error),
     }
 
     impl std::error::Error for Error {
@@ -196,8 +226,8 @@ mod error {
                 Self::WrongType => None,
                 Self::NotAllFeaturesSatisfied => None,
                 Self::FeatureAttributeMissing(_) => None,
-                Self::AttributeError(_, _) => None,
-                Self::IoError(_, ref e) => Some(e)
+                Self::InvalidAttribute(_, _) => None,
+                Self::Io(_, ref e) => Some(e)
             }
         }
     }
@@ -209,8 +239,8 @@ mod error {
                 Self::WrongType => write!(f, "template types do not match"),
                 Self::NotAllFeaturesSatisfied => write!(f, "not all features were built"),
                 Self::FeatureAttributeMissing(s) => write!(f, "feature attribute missing: {}", s),
-                Self::AttributeError(s, ref e) => write!(f, "feature attribute error: {} => {}", s, e),
-                Self::IoError(s, ref e) => write!(f, "io error while building feature: {} => {}", s, e.to_string()),
+                Self::InvalidAttribute(s, ref e) => write!(f, "feature attribute error: {} => {}", s, e),
+                Self::Io(s, ref e) => write!(f, "io error while building feature: {} => {}", s, e),
             }
         }
     }
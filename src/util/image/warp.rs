@@ -0,0 +1,145 @@
+use image::{GenericImage, Rgba, RgbaImage};
+use imageproc::drawing::draw_line_segment_mut;
+use imageproc::geometric_transformations::Projection;
+
+/// The four corners of a quadrilateral: upper left, upper right, lower right, lower left.
+/// Features placed in a quad are warped into it (e.g. onto a tilted sign)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quad(pub [(f32, f32); 4]);
+
+impl Quad {
+    pub fn from_box(x: u32, y: u32, w: u32, h: u32) -> Quad {
+        let (x, y, w, h) = (x as f32, y as f32, w as f32, h as f32);
+        Quad([(x, y), (x + w, y), (x + w, y + h), (x, y + h)])
+    }
+
+    /// Rotates the corners clockwise around their center
+    pub fn rotate(&self, degrees: f32) -> Quad {
+        let (cx, cy) = self.center();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut corners = self.0;
+        for c in corners.iter_mut() {
+            let (dx, dy) = (c.0 - cx, c.1 - cy);
+            *c = (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos);
+        }
+        Quad(corners)
+    }
+
+    pub fn translate(&self, dx: f32, dy: f32) -> Quad {
+        let mut corners = self.0;
+        for c in corners.iter_mut() {
+            *c = (c.0 + dx, c.1 + dy);
+        }
+        Quad(corners)
+    }
+
+    /// The box around the corners (x, y, w, h). Corners left of or above the image are cut off
+    pub fn bounds(&self) -> (u32, u32, u32, u32) {
+        let min_x = self.0.iter().map(|c| c.0).fold(f32::MAX, f32::min).max(0.0);
+        let min_y = self.0.iter().map(|c| c.1).fold(f32::MAX, f32::min).max(0.0);
+        let max_x = self.0.iter().map(|c| c.0).fold(0.0, f32::max);
+        let max_y = self.0.iter().map(|c| c.1).fold(0.0, f32::max);
+        (min_x as u32, min_y as u32, (max_x - min_x).ceil() as u32, (max_y - min_y).ceil() as u32)
+    }
+
+    /// The size of the content before it is warped. Opposite edges are averaged
+    pub fn size(&self) -> (u32, u32) {
+        let c = &self.0;
+        let w = (distance(c[0], c[1]) + distance(c[3], c[2])) / 2.0;
+        let h = (distance(c[0], c[3]) + distance(c[1], c[2])) / 2.0;
+        (w.round().max(1.0) as u32, h.round().max(1.0) as u32)
+    }
+
+    /// Only convex quads can be warped into
+    pub fn is_valid(&self) -> bool {
+        let mut sign = 0.0;
+        for i in 0..4 {
+            let cross = cross(self.0[i], self.0[(i + 1) % 4], self.0[(i + 2) % 4]);
+            if cross == 0.0 || cross.signum() * sign < 0.0 {
+                return false;
+            }
+            sign = cross.signum();
+        }
+        true
+    }
+
+    fn center(&self) -> (f32, f32) {
+        let (x, y) = self.0.iter().fold((0.0, 0.0), |acc, c| (acc.0 + c.0, acc.1 + c.1));
+        (x / 4.0, y / 4.0)
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let mut sign = 0.0;
+        for i in 0..4 {
+            let cross = cross(self.0[i], self.0[(i + 1) % 4], (x, y));
+            if cross.signum() * sign < 0.0 {
+                return false;
+            }
+            if cross != 0.0 {
+                sign = cross.signum();
+            }
+        }
+        true
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+fn cross(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0)
+}
+
+/// Warps the whole source image into the quad with a projective transformation and blends it onto dest
+pub fn warp_into_quad(source: &RgbaImage, dest: &mut RgbaImage, quad: &Quad) {
+    let (w, h) = (source.width() as f32, source.height() as f32);
+    // MAPS THE QUAD BACK ONTO THE SOURCE
+    let projection = match Projection::from_control_points(quad.0, [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)]) {
+        Some(p) => p,
+        None => return
+    };
+
+    let (bx, by, bw, bh) = quad.bounds();
+    for y in by..(by + bh + 1).min(dest.height()) {
+        for x in bx..(bx + bw + 1).min(dest.width()) {
+            let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+            if !quad.contains(cx, cy) {
+                continue;
+            }
+            let (sx, sy) = projection * (cx, cy);
+            if let Some(pixel) = sample_bilinear(source, sx - 0.5, sy - 0.5) {
+                dest.blend_pixel(x, y, pixel);
+            }
+        }
+    }
+}
+
+/// Draws the outline of the quad (used for debugging templates)
+pub fn draw_quad(dest: &mut RgbaImage, quad: &Quad, color: Rgba<u8>) {
+    for i in 0..4 {
+        draw_line_segment_mut(dest, quad.0[i], quad.0[(i + 1) % 4], color);
+    }
+}
+
+/// Mixes the four pixels around the position. Positions at the edge use the closest pixels
+fn sample_bilinear(img: &RgbaImage, x: f32, y: f32) -> Option<Rgba<u8>> {
+    let (w, h) = (img.width() as f32, img.height() as f32);
+    if x < -1.0 || y < -1.0 || x > w || y > h {
+        return None;
+    }
+    let x = x.max(0.0).min(w - 1.0);
+    let y = y.max(0.0).min(h - 1.0);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(img.width() - 1), (y0 + 1).min(img.height() - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let (p00, p10, p01, p11) = (img.get_pixel(x0, y0), img.get_pixel(x1, y0), img.get_pixel(x0, y1), img.get_pixel(x1, y1));
+    let mut out = [0u8; 4];
+    for (i, o) in out.iter_mut().enumerate() {
+        let top = p00[i] as f32 * (1.0 - fx) + p10[i] as f32 * fx;
+        let bottom = p01[i] as f32 * (1.0 - fx) + p11[i] as f32 * fx;
+        *o = (top * (1.0 - fy) + bottom * fy).round() as u8;
+    }
+    Some(Rgba(out))
+}
\ No newline at end of file
@@ -1,4 +1,8 @@
-pub mod image;
+// THE TEMPLATES LIVE IN THE LIBRARY, SO THE TEMPLATE TOOL CAN USE THEM, TOO
+pub use not_dank_memer::util::image;
 pub mod reddit;
 pub mod safe;
-pub mod eventwaiter;
\ No newline at end of file
+pub mod eventwaiter;
+pub mod guild_settings;
+pub mod concurrency;
+pub mod analytics;
\ No newline at end of file
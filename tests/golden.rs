@@ -0,0 +1,185 @@
+//! Renders every template with the same avatars and texts and compares the result with its reference image in tests/golden.
+//! After an intended change run the tests with NDM_UPDATE_GOLDEN=1 to write new references
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::thread;
+
+use image::{AnimationDecoder, DynamicImage, FilterType, GenericImageView, ImageFormat, Rgba, RgbaImage};
+
+use not_dank_memer::util::image::{ImageStorage, set_debug};
+use not_dank_memer::util::image::animation::ImageFrames;
+use not_dank_memer::util::image::feature::FeatureType;
+
+const UPDATE_VAR: &str = "NDM_UPDATE_GOLDEN";
+const TEMPLATES: &str = "./templates/";
+const REFERENCES: &str = "./tests/golden/";
+// THE RENDERS WHICH DO NOT MATCH AND THEIR DIFFS ARE WRITTEN THERE
+const FAILURES: &str = "./target/golden/";
+// EVERY FRAME IS COMPARED AT THIS SIZE. TINY DIFFERENCES (E.G. ANTI-ALIASING) ARE BLURRED AWAY AND THE REFERENCES STAY SMALL
+const COMPARE_SIZE: u32 = 256;
+// A PIXEL IS DIFFERENT IF ONE OF ITS CHANNELS DIFFERS BY MORE
+const PIXEL_TOLERANCE: i32 = 24;
+// A RENDER MATCHES ITS REFERENCE IF AT MOST THIS SHARE OF PIXELS IS DIFFERENT
+const MAX_DIFFERENT_PIXELS: f64 = 0.005;
+const AVATAR_SIZE: u32 = 128;
+const TEXTS: [&str; 3] = ["Top text", "When the golden tests pass on the first try", "Bottom text"];
+
+#[test]
+fn templates_match_references() {
+    // DEBUG BUILDS WOULD DRAW THE BOXES OF THE FEATURES
+    set_debug(false);
+    let images = ImageStorage::load(Path::new(TEMPLATES)).expect("could not load the templates");
+    let update = env::var(UPDATE_VAR).is_ok();
+    let mut keys = images.get_all_keys();
+    keys.sort();
+    assert!(!keys.is_empty(), "no templates found");
+
+    // RENDERING ALL TEMPLATES ONE AFTER ANOTHER TAKES A WHILE IN DEBUG BUILDS
+    let images = &images;
+    let renders: Vec<Result<RgbaImage, String>> = thread::scope(|s| {
+        let handles: Vec<_> = keys.iter().map(|key| s.spawn(move || render(images, key))).collect();
+        handles.into_iter().map(|h| h.join().unwrap_or_else(|_| Err("rendering panicked".to_owned()))).collect()
+    });
+
+    let mut failures = Vec::new();
+    for (key, render) in keys.iter().zip(renders) {
+        let actual = match render {
+            Ok(k) => k,
+            Err(e) => {
+                failures.push(format!("{}: could not be rendered: {}", key, e));
+                continue;
+            }
+        };
+        let reference = Path::new(REFERENCES).join(format!("{}.png", key));
+        if update {
+            fs::create_dir_all(REFERENCES).unwrap();
+            actual.save(&reference).unwrap();
+            continue;
+        }
+
+        let expected = match image::open(&reference) {
+            Ok(k) => k.to_rgba(),
+            Err(_) => {
+                failures.push(format!("{}: no reference image", key));
+                continue;
+            }
+        };
+        if let Err((message, diff)) = compare(&expected, &actual) {
+            fs::create_dir_all(FAILURES).unwrap();
+            actual.save(Path::new(FAILURES).join(format!("{}.png", key))).unwrap();
+            if let Some(diff) = diff {
+                diff.save(Path::new(FAILURES).join(format!("{}_diff.png", key))).unwrap();
+            }
+            failures.push(format!("{}: {}", key, message));
+        }
+    }
+
+    // REFERENCES OF REMOVED TEMPLATES
+    for entry in fs::read_dir(REFERENCES).map(|d| d.filter_map(|e| e.ok()).collect()).unwrap_or_else(|_| vec![]) {
+        let name = entry.file_name().to_string_lossy().trim_end_matches(".png").to_owned();
+        if !keys.contains(&name) {
+            if update {
+                fs::remove_file(entry.path()).unwrap();
+            } else {
+                failures.push(format!("{}: reference without template", name));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} template(s) do not match their reference (renders and diffs are in {}):\n{}\nRun the tests with {}=1 if the changes are intended",
+            failures.len(), FAILURES, failures.join("\n"), UPDATE_VAR);
+}
+
+/// Animated templates are compared as a strip of all their frames
+fn render(images: &ImageStorage, key: &str) -> Result<RgbaImage, String> {
+    let mut template = images.start_building(key).ok_or("template not found")?;
+    let features = images.get_required_features(key).ok_or("template not found")?;
+
+    let (mut avatars, mut texts) = (0, 0);
+    for f in features.iter() {
+        let result = match f.kind {
+            FeatureType::Image => template.set_image(&f.key),
+            FeatureType::SplitText | FeatureType::Text => {
+                texts += 1;
+                template.set_text(&f.key, TEXTS[(texts - 1) % TEXTS.len()].to_owned())
+            }
+            FeatureType::UserImage => {
+                avatars += 1;
+                template.set_user_image(&f.key, ImageFrames::still(avatar(avatars - 1)))
+            }
+        };
+        result.map_err(|e| e.to_string())?;
+    }
+
+    let template = template.build().map_err(|e| e.to_string())?;
+    let buf = template.apply().map_err(|e| e.to_string())?;
+    let frames: Vec<DynamicImage> = if template.is_animated() {
+        let decoder = image::gif::Decoder::new(Cursor::new(buf)).map_err(|e| e.to_string())?;
+        decoder.into_frames().collect_frames().map_err(|e| e.to_string())?
+            .into_iter().map(|f| DynamicImage::ImageRgba8(f.into_buffer())).collect()
+    } else {
+        vec![image::load_from_memory_with_format(&buf, ImageFormat::PNG).map_err(|e| e.to_string())?]
+    };
+    Ok(strip(&frames))
+}
+
+/// Scales the frames down and puts them next to each other
+fn strip(frames: &[DynamicImage]) -> RgbaImage {
+    let frames: Vec<DynamicImage> = frames.iter().map(|f| f.resize(COMPARE_SIZE, COMPARE_SIZE, FilterType::Triangle)).collect();
+    let (w, h) = frames[0].dimensions();
+    let mut out = RgbaImage::new(w * frames.len() as u32, h);
+    for (i, frame) in frames.iter().enumerate() {
+        image::imageops::replace(&mut out, frame, i as u32 * w, 0);
+    }
+    out
+}
+
+/// The error contains the diff image (different pixels are red) if both images have the same size
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> Result<(), (String, Option<RgbaImage>)> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err((format!("size is {:?} instead of {:?}", actual.dimensions(), expected.dimensions()), None));
+    }
+
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    let mut different = 0u64;
+    for (x, y, e) in expected.enumerate_pixels() {
+        let (e, a) = (premultiply(e), premultiply(actual.get_pixel(x, y)));
+        let distance = (0..4).map(|c| (e[c] as i32 - a[c] as i32).abs()).max().unwrap_or_default();
+        if distance > PIXEL_TOLERANCE {
+            different += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let gray = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 2) as u8; // DARKENED, SO THE RED STANDS OUT
+            diff.put_pixel(x, y, Rgba([gray, gray, gray, 255]));
+        }
+    }
+
+    let share = different as f64 / (expected.width() * expected.height()) as f64;
+    if share > MAX_DIFFERENT_PIXELS {
+        return Err((format!("{:.2}% of the pixels are different", share * 100.0), Some(diff)));
+    }
+    Ok(())
+}
+
+/// Transparent pixels are equal no matter their color
+fn premultiply(p: &Rgba<u8>) -> [u8; 4] {
+    let a = p[3] as u32;
+    [(p[0] as u32 * a / 255) as u8, (p[1] as u32 * a / 255) as u8, (p[2] as u32 * a / 255) as u8, p[3]]
+}
+
+/// A gradient with a white corner in the upper left, so cropping, anchors and masks show up in the renders
+fn avatar(index: usize) -> DynamicImage {
+    let shift = (index * 85) as u32;
+    let corner = AVATAR_SIZE / 4;
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(AVATAR_SIZE, AVATAR_SIZE, |x, y| {
+        if x < corner && y < corner {
+            return Rgba([255, 255, 255, 255]);
+        }
+        let r = ((x * 255 / AVATAR_SIZE + shift) % 256) as u8;
+        let g = ((y * 255 / AVATAR_SIZE + shift) % 256) as u8;
+        let b = if (x / 16 + y / 16) % 2 == 0 { 200 } else { 40 };
+        Rgba([r, g, b, 255])
+    }))
+}
\ No newline at end of file